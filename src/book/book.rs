@@ -4,16 +4,86 @@ use std::fs::{self, File};
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 
-use super::summary::{parse_summary, Link, SectionNumber, Summary, SummaryItem};
+use super::summary::{parse_summary, Link, Part, SectionNumber, Severity, Summary, SummaryItem};
 use crate::config::BuildConfig;
 use crate::errors::*;
-use crate::utils::bracket_escape;
-use log::debug;
+use crate::utils::{bracket_escape, slugify};
+use log::{debug, error, warn};
 use serde::{Deserialize, Serialize};
 
 /// Load a book into memory from its `src/` directory.
+///
+/// If `cfg.languages` is configured, this loads the configured default
+/// language. Use [`load_book_for_language`] to select a specific translation.
 pub fn load_book<P: AsRef<Path>>(src_dir: P, cfg: &BuildConfig) -> Result<Book> {
+    load_book_for_language(src_dir, cfg, None)
+}
+
+/// Load a book into memory, optionally selecting one of the book's
+/// `languages`.
+///
+/// When `cfg.languages` is non-empty, `src_dir/<language>/` is treated as the
+/// root containing that translation's `SUMMARY.md`. If `language` is `None`,
+/// the configured default language is used. Chapters missing from the
+/// selected translation but present in the default language are loaded from
+/// the default language instead of erroring, so incomplete translations
+/// still produce a full book.
+pub fn load_book_for_language<P: AsRef<Path>>(
+    src_dir: P,
+    cfg: &BuildConfig,
+    language: Option<&str>,
+) -> Result<Book> {
     let src_dir = src_dir.as_ref();
+
+    if cfg.languages.is_empty() {
+        return load_book_at(src_dir, cfg, None);
+    }
+
+    let default_language = default_language_code(cfg)?;
+    let language = language.unwrap_or(&default_language);
+    if !cfg.languages.contains_key(language) {
+        bail!("Unknown language {:?}, not found in `languages`", language);
+    }
+
+    let language_dir = src_dir.join(language_src_subdir(cfg, language));
+    let fallback_dir = if language == default_language {
+        None
+    } else {
+        Some(src_dir.join(language_src_subdir(cfg, &default_language)))
+    };
+
+    load_book_at(&language_dir, cfg, fallback_dir.as_deref())
+}
+
+/// The source subdirectory (relative to `src_dir`) holding `code`'s
+/// `SUMMARY.md`: its `[build.languages.<code>].src` override if set,
+/// otherwise `code` itself.
+fn language_src_subdir(cfg: &BuildConfig, code: &str) -> PathBuf {
+    cfg.languages
+        .get(code)
+        .and_then(|entry| entry.src.clone())
+        .unwrap_or_else(|| PathBuf::from(code))
+}
+
+/// Find the code of the language marked `default = true` in `cfg.languages`.
+fn default_language_code(cfg: &BuildConfig) -> Result<String> {
+    let mut defaults = cfg.languages.iter().filter(|(_, lang)| lang.default);
+
+    let (code, _) = defaults
+        .next()
+        .ok_or_else(|| Error::msg("No default language set, exactly one `languages` entry must have `default = true`"))?;
+
+    ensure!(
+        defaults.next().is_none(),
+        "More than one default language set, exactly one `languages` entry must have `default = true`"
+    );
+
+    Ok(code.clone())
+}
+
+/// Load the `SUMMARY.md` under `src_dir` and the chapters it references,
+/// falling back to `fallback_dir` for chapters that can't be found locally.
+fn load_book_at(src_dir: &Path, cfg: &BuildConfig, fallback_dir: Option<&Path>) -> Result<Book> {
     let summary_md = src_dir.join("SUMMARY.md");
 
     let mut summary_content = String::new();
@@ -22,14 +92,20 @@ pub fn load_book<P: AsRef<Path>>(src_dir: P, cfg: &BuildConfig) -> Result<Book>
         .read_to_string(&mut summary_content)?;
     //dbg!(&summary_content);
 
-    let summary = parse_summary(&summary_content)
+    let (summary, diagnostics) = parse_summary(&summary_content)
         .with_context(|| format!("Summary parsing failed for file={:?}", summary_md))?;
+    for diagnostic in &diagnostics {
+        match diagnostic.severity {
+            Severity::Error => error!("{}: {}", summary_md.display(), diagnostic),
+            Severity::Warning => warn!("{}: {}", summary_md.display(), diagnostic),
+        }
+    }
     //dbg!(&summary);
     if cfg.create_missing {
         create_missing(src_dir, &summary).with_context(|| "Unable to create missing chapters")?;
     }
     //create_fpm_ftd(&summary_content,&src_dir).with_context(|| "Unable to copy across static files")?;
-    load_book_from_disk(&summary, src_dir)
+    load_book_from_disk_with_fallback(&summary, src_dir, cfg, fallback_dir)
 }
 /*fn create_fpm_ftd(summary_content:&String,src_dir: &Path) -> Result<()> {
     //dbg!(&src_dir);
@@ -56,10 +132,12 @@ pub fn load_book<P: AsRef<Path>>(src_dir: P, cfg: &BuildConfig) -> Result<Book>
         Ok(())
 }*/
 fn create_missing(src_dir: &Path, summary: &Summary) -> Result<()> {
+    let numbered = summary.parts.iter().flat_map(|p| p.numbered_chapters.iter());
+
     let mut items: Vec<_> = summary
         .prefix_chapters
         .iter()
-        .chain(summary.numbered_chapters.iter())
+        .chain(numbered)
         .chain(summary.suffix_chapters.iter())
         .collect();
 
@@ -187,6 +265,9 @@ pub struct Chapter {
     pub source_path: Option<PathBuf>,
     /// An ordered list of the names of each chapter above this one in the hierarchy.
     pub parent_names: Vec<String>,
+    /// The `#fragment` the chapter's `SUMMARY.md` link pointed at, if any,
+    /// e.g. the `section` in `[Foo](foo.md#section)`.
+    pub anchor: Option<String>,
 }
 
 impl Chapter {
@@ -210,20 +291,26 @@ impl Chapter {
 
     /// Create a new draft chapter that is not attached to a source markdown file (and thus
     /// has no content).
+    ///
+    /// A placeholder output path is still assigned (derived from the
+    /// chapter's position in the book and its name), so the renderer can emit
+    /// a stub page and the sidebar's link never dangles.
     pub fn new_draft(name: &str, parent_names: Vec<String>) -> Self {
+        let path = draft_chapter_path(name, &parent_names);
+
         Chapter {
             name: name.to_string(),
             content: String::new(),
-            path: None,
+            path: Some(path),
             source_path: None,
             parent_names,
             ..Default::default()
         }
     }
 
-    /// Check if the chapter is a draft chapter, meaning it has no path to a source markdown file.
+    /// Check if the chapter is a draft chapter, meaning it has no source markdown file.
     pub fn is_draft_chapter(&self) -> bool {
-        self.path.is_none()
+        self.source_path.is_none()
     }
 }
 
@@ -231,20 +318,64 @@ impl Chapter {
 ///
 /// You need to pass in the book's source directory because all the links in
 /// `SUMMARY.md` give the chapter locations relative to it.
-pub(crate) fn load_book_from_disk<P: AsRef<Path>>(summary: &Summary, src_dir: P) -> Result<Book> {
+pub(crate) fn load_book_from_disk<P: AsRef<Path>>(
+    summary: &Summary,
+    src_dir: P,
+    cfg: &BuildConfig,
+) -> Result<Book> {
+    load_book_from_disk_with_fallback(summary, src_dir, cfg, None)
+}
+
+/// Like [`load_book_from_disk`], but chapters missing under `src_dir` are
+/// looked up under `fallback_dir` (if given) before giving up.
+fn load_book_from_disk_with_fallback<P: AsRef<Path>>(
+    summary: &Summary,
+    src_dir: P,
+    cfg: &BuildConfig,
+    fallback_dir: Option<&Path>,
+) -> Result<Book> {
     debug!("Loading the book from disk");
     let src_dir = src_dir.as_ref();
+    let disallow_drafts = cfg.disallow_draft_chapters;
 
-    let prefix = summary.prefix_chapters.iter();
-    let numbered = summary.numbered_chapters.iter();
-    let suffix = summary.suffix_chapters.iter();
+    let mut chapters = Vec::new();
+
+    for summary_item in &summary.prefix_chapters {
+        let chapter = load_summary_item(
+            summary_item,
+            src_dir,
+            Vec::new(),
+            fallback_dir,
+            disallow_drafts,
+        )?;
+        chapters.push(chapter);
+    }
 
-    let summary_items = prefix.chain(numbered).chain(suffix);
+    for part in &summary.parts {
+        if let Some(ref title) = part.title {
+            chapters.push(BookItem::PartTitle(title.clone()));
+        }
 
-    let mut chapters = Vec::new();
+        for summary_item in &part.numbered_chapters {
+            let chapter = load_summary_item(
+                summary_item,
+                src_dir,
+                Vec::new(),
+                fallback_dir,
+                disallow_drafts,
+            )?;
+            chapters.push(chapter);
+        }
+    }
 
-    for summary_item in summary_items {
-        let chapter = load_summary_item(summary_item, src_dir, Vec::new())?;
+    for summary_item in &summary.suffix_chapters {
+        let chapter = load_summary_item(
+            summary_item,
+            src_dir,
+            Vec::new(),
+            fallback_dir,
+            disallow_drafts,
+        )?;
         chapters.push(chapter);
     }
 
@@ -255,32 +386,78 @@ fn load_summary_item<P: AsRef<Path> + Clone>(
     item: &SummaryItem,
     src_dir: P,
     parent_names: Vec<String>,
+    fallback_dir: Option<&Path>,
+    disallow_drafts: bool,
 ) -> Result<BookItem> {
     match item {
         SummaryItem::Separator => Ok(BookItem::Separator),
         SummaryItem::Link(ref link) => {
-            load_chapter(link, src_dir, parent_names).map(BookItem::Chapter)
+            load_chapter(link, src_dir, parent_names, fallback_dir, disallow_drafts)
+                .map(BookItem::Chapter)
         }
         SummaryItem::PartTitle(title) => Ok(BookItem::PartTitle(title.clone())),
     }
 }
 
+/// Derive a stable output path for a draft chapter from its name and the
+/// names of its ancestors, since `SUMMARY.md` gives it no file of its own.
+fn draft_chapter_path(name: &str, parent_names: &[String]) -> PathBuf {
+    let mut path: PathBuf = parent_names.iter().map(|p| slugify(p)).collect();
+    path.push(slugify(name));
+    path.with_extension("md")
+}
+
+/// Split a `#fragment` off the end of a chapter location, if present.
+fn split_location_anchor(location: &Path) -> (PathBuf, Option<String>) {
+    match location.to_str().and_then(|s| s.split_once('#')) {
+        Some((path, anchor)) => (PathBuf::from(path), Some(anchor.to_string())),
+        None => (location.to_path_buf(), None),
+    }
+}
+
 fn load_chapter<P: AsRef<Path>>(
     link: &Link,
     src_dir: P,
     parent_names: Vec<String>,
+    fallback_dir: Option<&Path>,
+    disallow_drafts: bool,
 ) -> Result<Chapter> {
     let src_dir = src_dir.as_ref();
 
     let mut ch = if let Some(ref link_location) = link.location {
         debug!("Loading {} ({})", link.name, link_location.display());
 
+        // Defensively split off a `#fragment`, in case a `Link` was built by
+        // hand rather than parsed from `SUMMARY.md` (where it's already
+        // split out into `Link::anchor`).
+        let (link_location, location_anchor) = split_location_anchor(link_location);
+        let link_location = &link_location;
+
         let location = if link_location.is_absolute() {
             link_location.clone()
         } else {
             src_dir.join(link_location)
         };
 
+        // If the chapter isn't translated yet, fall back to the default
+        // language's copy so incomplete translations still produce a full
+        // book.
+        let (location, effective_src_dir) = if !location.exists() && !link_location.is_absolute()
+        {
+            match fallback_dir {
+                Some(fallback_dir) if fallback_dir.join(link_location).exists() => {
+                    debug!(
+                        "{} not found, falling back to the default language",
+                        link_location.display()
+                    );
+                    (fallback_dir.join(link_location), fallback_dir)
+                }
+                _ => (location, src_dir),
+            }
+        } else {
+            (location, src_dir)
+        };
+
         let mut f = File::open(&location)
             .with_context(|| format!("Chapter file not found, {}", link_location.display()))?;
 
@@ -294,11 +471,18 @@ fn load_chapter<P: AsRef<Path>>(
         }
 
         let stripped = location
-            .strip_prefix(&src_dir)
+            .strip_prefix(&effective_src_dir)
             .expect("Chapters are always inside a book");
 
-        Chapter::new(&link.name, content, stripped, parent_names.clone())
+        let mut chapter = Chapter::new(&link.name, content, stripped, parent_names.clone());
+        chapter.anchor = link.anchor.clone().or(location_anchor);
+        chapter
     } else {
+        ensure!(
+            !disallow_drafts,
+            "Draft chapter \"{}\" found, but drafts are disallowed by `build.disallow-draft-chapters`",
+            link.name
+        );
         Chapter::new_draft(&link.name, parent_names.clone())
     };
 
@@ -310,7 +494,15 @@ fn load_chapter<P: AsRef<Path>>(
     let sub_items = link
         .nested_items
         .iter()
-        .map(|i| load_summary_item(i, src_dir, sub_item_parents.clone()))
+        .map(|i| {
+            load_summary_item(
+                i,
+                src_dir,
+                sub_item_parents.clone(),
+                fallback_dir,
+                disallow_drafts,
+            )
+        })
         .collect::<Result<Vec<_>>>()?;
 
     ch.sub_items = sub_items;
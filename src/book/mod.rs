@@ -5,22 +5,27 @@
 mod book;
 mod summary;
 
-pub use self::book::{load_book, Book, BookItem, BookItems, Chapter};
+pub use self::book::{load_book, load_book_for_language, Book, BookItem, BookItems, Chapter};
 //pub use self::init::BookBuilder;
-pub use self::summary::{parse_summary, Link, SectionNumber, Summary, SummaryItem};
+pub use self::summary::{
+    parse_summary, Link, Part, ParseDiagnostic, SectionNumber, Severity, Summary, SummaryItem,
+};
 
 use log::{debug, error, info, log_enabled, trace, warn};
+use std::collections::HashMap;
 use std::io::Write;
 use std::path::PathBuf;
 use std::process::Command;
 use std::string::ToString;
+use std::thread;
 use tempfile::Builder as TempFileBuilder;
 use toml::Value;
 use topological_sort::TopologicalSort;
 
 use crate::errors::*;
 use crate::preprocess::{
-    CmdPreprocessor, IndexPreprocessor, LinkPreprocessor, Preprocessor, PreprocessorContext,
+    CmdPreprocessor, IncludePreprocessor, IndexPreprocessor, LinkPreprocessor, Preprocessor,
+    PreprocessorContext,
 };
 use crate::renderer::{CmdRenderer, HtmlHandlebars, MarkdownRenderer, RenderContext, Renderer};
 use crate::utils;
@@ -72,7 +77,7 @@ impl MDBook {
         let src_dir = root.join(&config.book.src);
         let book = book::load_book(&src_dir, &config.build)?;
 
-        let renderers = determine_renderers(&config);
+        let renderers = determine_renderers(&config)?;
         let preprocessors = determine_preprocessors(&config)?;
 
         Ok(MDBook {
@@ -93,9 +98,9 @@ impl MDBook {
         let root = book_root.into();
 
         let src_dir = root.join(&config.book.src);
-        let book = book::load_book_from_disk(&summary, &src_dir)?;
+        let book = book::load_book_from_disk(&summary, &src_dir, &config.build)?;
 
-        let renderers = determine_renderers(&config);
+        let renderers = determine_renderers(&config)?;
         let preprocessors = determine_preprocessors(&config)?;
 
         Ok(MDBook {
@@ -114,14 +119,139 @@ impl MDBook {
 
 
     /// Tells the renderer to build our book and put it in the build directory.
+    ///
+    /// When `build.languages` is configured, this builds every declared
+    /// translation in turn (each loaded via [`load_book_for_language`] and
+    /// rendered with its [`crate::config::Config::effective_book_config`]),
+    /// writing each one under its own `<lang>/` subdirectory of
+    /// [`MDBook::build_dir_for`]. Otherwise it builds the single book loaded
+    /// at construction time.
     pub fn build(&self) -> Result<()> {
+        if self.config.build.languages.is_empty() {
+            return self.build_translation(&self.book, &self.config, None);
+        }
+
+        let src_dir = self.source_dir();
+        for code in self.config.build.languages.keys() {
+            info!("Building the {:?} translation", code);
+            let book = book::load_book_for_language(&src_dir, &self.config.build, Some(code.as_str()))?;
+            let mut config = self.config.clone();
+            config.book = self.config.effective_book_config(code);
+            self.build_translation(&book, &config, Some(code.as_str()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Preprocess and render `book` (under `config`), writing each
+    /// renderer's output to `build_dir_for(renderer.name())`, nested under
+    /// `lang` when building one of several translations.
+    ///
+    /// Renderers that select the exact same ordered set of preprocessors
+    /// reuse one preprocessed [`Book`] instead of re-running the chain, and
+    /// the independent per-renderer render steps run concurrently.
+    fn build_translation(&self, book: &Book, config: &Config, lang: Option<&str>) -> Result<()> {
+        let mut preprocessed_cache: HashMap<Vec<String>, (Book, HashMap<PathBuf, String>)> =
+            HashMap::new();
+        let mut render_contexts = Vec::with_capacity(self.renderers.len());
+
         for renderer in &self.renderers {
-            self.execute_build_process(&**renderer)?;
+            let chain = self.preprocessor_chain_for(&**renderer);
+
+            if !preprocessed_cache.contains_key(&chain) {
+                preprocessed_cache.insert(
+                    chain.clone(),
+                    self.run_preprocessor_chain(book, config, &chain, renderer.name())?,
+                );
+            } else {
+                debug!(
+                    "Reusing the preprocessed book already built for the \"{}\" chain",
+                    chain.join(", ")
+                );
+            }
+
+            let (book, chapter_titles) = preprocessed_cache
+                .get(&chain)
+                .expect("just inserted above if missing");
+            let mut build_dir = self.build_dir_for(renderer.name());
+            if let Some(lang) = lang {
+                build_dir = build_dir.join(lang);
+            }
+            let mut render_context = RenderContext::new(
+                self.root.clone(),
+                book.clone(),
+                config.clone(),
+                build_dir,
+            );
+            render_context.chapter_titles.extend(chapter_titles.clone());
+            render_contexts.push((renderer.as_ref(), render_context));
+        }
+
+        let results: Vec<Result<()>> = thread::scope(|scope| {
+            render_contexts
+                .iter()
+                .map(|(renderer, ctx)| {
+                    scope.spawn(move || {
+                        info!("Running the {} backend", renderer.name());
+                        renderer.render(ctx).with_context(|| "Rendering failed")
+                    })
+                })
+                .collect::<Vec<_>>()
+                .into_iter()
+                .map(|handle| handle.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+                .collect()
+        });
+
+        for result in results {
+            result?;
         }
 
         Ok(())
     }
 
+    /// The ordered list of preprocessor names `preprocessor_should_run`
+    /// selects for `renderer`, used to key the preprocessed-book cache in
+    /// [`MDBook::build_translation`].
+    fn preprocessor_chain_for(&self, renderer: &dyn Renderer) -> Vec<String> {
+        self.preprocessors
+            .iter()
+            .filter(|preprocessor| {
+                preprocessor_should_run(&***preprocessor, renderer.name(), &self.config)
+            })
+            .map(|preprocessor| preprocessor.name().to_string())
+            .collect()
+    }
+
+    /// Run exactly the preprocessors named in `chain`, in order, returning
+    /// the resulting [`Book`] and the chapter titles they recorded.
+    fn run_preprocessor_chain(
+        &self,
+        book: &Book,
+        config: &Config,
+        chain: &[String],
+        renderer_name: &str,
+    ) -> Result<(Book, HashMap<PathBuf, String>)> {
+        let preprocess_ctx = PreprocessorContext::new(
+            self.root.clone(),
+            config.clone(),
+            renderer_name.to_string(),
+        );
+
+        let mut book = book.clone();
+        for name in chain {
+            let preprocessor = self
+                .preprocessors
+                .iter()
+                .find(|preprocessor| preprocessor.name() == name)
+                .expect("chain only contains names drawn from self.preprocessors");
+            debug!("Running the {} preprocessor.", preprocessor.name());
+            book = preprocessor.run(&preprocess_ctx, book)?;
+        }
+
+        let chapter_titles = preprocess_ctx.chapter_titles.borrow_mut().drain().collect();
+        Ok((book, chapter_titles))
+    }
+
     /// Run the entire build process for a particular [`Renderer`].
     pub fn execute_build_process(&self, renderer: &dyn Renderer) -> Result<()> {
         //dbg!("build process");
@@ -134,13 +264,12 @@ impl MDBook {
         );
         //dbg!(&preprocess_ctx);
         for preprocessor in &self.preprocessors {
-            if preprocessor_should_run(&**preprocessor, renderer, &self.config) {
+            if preprocessor_should_run(&**preprocessor, renderer.name(), &self.config) {
                 debug!("Running the {} preprocessor.", preprocessor.name());
                 preprocessed_book = preprocessor.run(&preprocess_ctx, preprocessed_book)?;
             }
         }
         let name = renderer.name();
-        dbg!(&name);
         let build_dir = self.build_dir_for(name);
 
         let mut render_context = RenderContext::new(
@@ -193,17 +322,30 @@ impl MDBook {
 
         let mut chapter_found = false;
 
-        // FIXME: Is "test" the proper renderer name to use here?
         let preprocess_context =
             PreprocessorContext::new(self.root.clone(), self.config.clone(), "test".to_string());
 
-        let book = LinkPreprocessor::new().run(&preprocess_context, self.book.clone())?;
-        // Index Preprocessor is disabled so that chapter paths continue to point to the
-        // actual markdown files.
+        let mut book = self.book.clone();
+        for preprocessor in &self.preprocessors {
+            // Index Preprocessor is disabled so that chapter paths continue to point to the
+            // actual markdown files.
+            if preprocessor.name() == IndexPreprocessor::NAME {
+                continue;
+            }
+
+            if preprocessor_should_run(&**preprocessor, "test", &self.config) {
+                debug!("Running the {} preprocessor.", preprocessor.name());
+                book = preprocessor.run(&preprocess_context, book)?;
+            }
+        }
 
         let mut failed = false;
         for item in book.iter() {
             if let BookItem::Chapter(ref ch) = *item {
+                if ch.is_draft_chapter() {
+                    continue;
+                }
+
                 let chapter_path = match ch.path {
                     Some(ref path) if !path.as_os_str().is_empty() => path,
                     _ => continue,
@@ -293,20 +435,90 @@ impl MDBook {
     }
 }
 
-/// Look at the `Config` and try to figure out what renderers to use.
-fn determine_renderers(config: &Config) -> Vec<Box<dyn Renderer>> {
-    let mut renderers = Vec::new();
+/// Look at the `Config` and try to figure out what renderers to use,
+/// honoring any `before`/`after` ordering declared under each
+/// `[output.<name>]` table (e.g. a packaging backend that needs to run
+/// after `html` has written its output).
+fn determine_renderers(config: &Config) -> Result<Vec<Box<dyn Renderer>>> {
+    let output_table = match config.get("output").and_then(Value::as_table) {
+        Some(output_table) => output_table,
+        None => return Ok(vec![Box::new(HtmlHandlebars::new())]),
+    };
+
+    let mut renderer_names = TopologicalSort::<String>::new();
+
+    for (name, table) in output_table.iter() {
+        renderer_names.insert(name.to_string());
+
+        let exists = |name| output_table.contains_key(name);
+
+        if let Some(before) = table.get("before") {
+            let before = before.as_array().ok_or_else(|| {
+                Error::msg(format!("Expected output.{}.before to be an array", name))
+            })?;
+            for after in before {
+                let after = after.as_str().ok_or_else(|| {
+                    Error::msg(format!("Expected output.{}.before to contain strings", name))
+                })?;
 
-    if let Some(output_table) = config.get("output").and_then(Value::as_table) {
-        renderers.extend(output_table.iter().map(|(key, table)| {
-            if key == "html" {
-                Box::new(HtmlHandlebars::new()) as Box<dyn Renderer>
-            } else if key == "markdown" {
-                Box::new(MarkdownRenderer::new()) as Box<dyn Renderer>
-            } else {
-                interpret_custom_renderer(key, table)
+                if !exists(after) {
+                    // Only warn so that backends can be toggled on and off freely.
+                    warn!(
+                        "output.{}.before contains \"{}\", which was not found",
+                        name, after
+                    );
+                } else {
+                    renderer_names.add_dependency(name, after);
+                }
+            }
+        }
+
+        if let Some(after) = table.get("after") {
+            let after = after.as_array().ok_or_else(|| {
+                Error::msg(format!("Expected output.{}.after to be an array", name))
+            })?;
+            for before in after {
+                let before = before.as_str().ok_or_else(|| {
+                    Error::msg(format!("Expected output.{}.after to contain strings", name))
+                })?;
+
+                if !exists(before) {
+                    // See equivalent warning above for rationale
+                    warn!(
+                        "output.{}.after contains \"{}\", which was not found",
+                        name, before
+                    );
+                } else {
+                    renderer_names.add_dependency(before, name);
+                }
             }
-        }));
+        }
+    }
+
+    // Now that all links have been established, queue renderers in a stable,
+    // topologically-sorted order.
+    let mut names = Vec::with_capacity(renderer_names.len());
+    for mut batch in
+        std::iter::repeat_with(|| renderer_names.pop_all()).take_while(|batch| !batch.is_empty())
+    {
+        batch.sort();
+        names.extend(batch);
+    }
+
+    if !renderer_names.is_empty() {
+        return Err(Error::msg("Cyclic dependency detected in output backends"));
+    }
+
+    let mut renderers = Vec::with_capacity(names.len());
+    for name in &names {
+        let table = &output_table[name];
+        renderers.push(if name == "html" {
+            Box::new(HtmlHandlebars::new()) as Box<dyn Renderer>
+        } else if name == "markdown" {
+            Box::new(MarkdownRenderer::new()) as Box<dyn Renderer>
+        } else {
+            interpret_custom_renderer(name, table)
+        });
     }
 
     // if we couldn't find anything, add the HTML renderer as a default
@@ -314,14 +526,16 @@ fn determine_renderers(config: &Config) -> Vec<Box<dyn Renderer>> {
         renderers.push(Box::new(HtmlHandlebars::new()));
     }
 
-    renderers
+    Ok(renderers)
 }
 
-const DEFAULT_PREPROCESSORS: &[&str] = &["links", "index"];
+const DEFAULT_PREPROCESSORS: &[&str] = &["links", "index", "include"];
 
 fn is_default_preprocessor(pre: &dyn Preprocessor) -> bool {
     let name = pre.name();
-    name == LinkPreprocessor::NAME || name == IndexPreprocessor::NAME
+    name == LinkPreprocessor::NAME
+        || name == IndexPreprocessor::NAME
+        || name == IncludePreprocessor::NAME
 }
 
 /// Look at the `MDBook` and try to figure out what preprocessors to run.
@@ -413,11 +627,16 @@ fn determine_preprocessors(config: &Config) -> Result<Vec<Box<dyn Preprocessor>>
             let preprocessor: Box<dyn Preprocessor> = match name.as_str() {
                 "links" => Box::new(LinkPreprocessor::new()),
                 "index" => Box::new(IndexPreprocessor::new()),
+                "include" => Box::new(IncludePreprocessor::new()),
                 _ => {
                    
                     let table = &config.get("preprocessor").unwrap().as_table().unwrap()[&name];
                     let command = get_custom_preprocessor_cmd(&name, table);
-                    Box::new(CmdPreprocessor::new(name, command))
+                    let optional = table
+                        .get("optional")
+                        .and_then(Value::as_bool)
+                        .unwrap_or(false);
+                    Box::new(CmdPreprocessor::new(name, command).with_optional(optional))
                 }
             };
             preprocessors.push(preprocessor);
@@ -454,16 +673,15 @@ fn interpret_custom_renderer(key: &str, table: &Value) -> Box<CmdRenderer> {
 
 fn preprocessor_should_run(
     preprocessor: &dyn Preprocessor,
-    renderer: &dyn Renderer,
+    renderer_name: &str,
     cfg: &Config,
 ) -> bool {
     // default preprocessors should be run by default (if supported)
     if cfg.build.use_default_preprocessors && is_default_preprocessor(preprocessor) {
-        return preprocessor.supports_renderer(renderer.name());
+        return preprocessor.supports_renderer(renderer_name);
     }
 
     let key = format!("preprocessor.{}.renderers", preprocessor.name());
-    let renderer_name = renderer.name();
 
     if let Some(Value::Array(ref explicit_renderers)) = cfg.get(&key) {
         return explicit_renderers
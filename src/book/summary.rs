@@ -10,11 +10,47 @@ use std::path::{Path, PathBuf};
 
 /// All other elements are unsupported and will be ignored at best or result in
 /// an error.
-pub fn parse_summary(summary: &str) -> Result<Summary> {
+///
+/// Recoverable problems (e.g. a malformed nested chapter item) are collected
+/// into the returned `Vec<ParseDiagnostic>` rather than aborting the parse, so
+/// a caller can report every problem in `SUMMARY.md` at once instead of
+/// fixing and rerunning one mistake at a time. Only a structural failure that
+/// leaves the parser with no sensible way to continue is returned as an
+/// `Err`.
+pub fn parse_summary(summary: &str) -> Result<(Summary, Vec<ParseDiagnostic>)> {
     let parser = SummaryParser::new(summary);
     parser.parse()
 }
 
+/// How serious a [`ParseDiagnostic`] is.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Severity {
+    /// Parsing continued, but the author should take a look.
+    Warning,
+    /// The offending item was dropped from the `Summary`.
+    Error,
+}
+
+/// A single problem found while parsing `SUMMARY.md`, positioned at the
+/// `(line, column)` in the source that triggered it.
+#[derive(Debug, Clone)]
+pub struct ParseDiagnostic {
+    /// 1-based line number.
+    pub line: usize,
+    /// 1-based column number.
+    pub col: usize,
+    /// A human-readable description of the problem.
+    pub message: String,
+    /// How serious the problem is.
+    pub severity: Severity,
+}
+
+impl Display for ParseDiagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.col, self.message)
+    }
+}
+
 /// The parsed `SUMMARY.md`, specifying how the book should be laid out.
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct Summary {
@@ -23,11 +59,29 @@ pub struct Summary {
     /// Chapters before the main text (e.g. an introduction).
     pub prefix_chapters: Vec<SummaryItem>,
     /// The main numbered chapters of the book, broken into one or more possibly named parts.
-    pub numbered_chapters: Vec<SummaryItem>,
+    pub parts: Vec<Part>,
     /// Items which come after the main document (e.g. a conclusion).
     pub suffix_chapters: Vec<SummaryItem>,
 }
 
+/// A group of numbered chapters, optionally introduced by a `# Part Title`
+/// heading in `SUMMARY.md`.
+///
+/// Section numbers are continuous across parts, so the loader can tell
+/// readers and renderers where one titled group ends and the next begins
+/// without losing the numbering scheme.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Part {
+    /// The part's title, if the `SUMMARY.md` author gave it one.
+    pub title: Option<String>,
+    /// The numbered chapters belonging to this part.
+    pub numbered_chapters: Vec<SummaryItem>,
+    /// The section number of this part's first root-level chapter, so a
+    /// renderer can label the part's group header without re-scanning every
+    /// chapter above it. Numbering is continuous across parts.
+    pub start_number: SectionNumber,
+}
+
 /// A struct representing an entry in the `SUMMARY.md`, possibly with nested
 /// entries.
 ///
@@ -41,6 +95,9 @@ pub struct Link {
     pub location: Option<PathBuf>,
     /// The section number, if this chapter is in the numbered section.
     pub number: Option<SectionNumber>,
+    /// The `#fragment` the link pointed at, if any, e.g. the `section` in
+    /// `[Foo](foo.md#section)`.
+    pub anchor: Option<String>,
     /// Any nested items this chapter may contain.
     pub nested_items: Vec<SummaryItem>,
 }
@@ -52,6 +109,7 @@ impl Link {
             name: name.into(),
             location: Some(location.as_ref().to_path_buf()),
             number: None,
+            anchor: None,
             nested_items: Vec::new(),
         }
     }
@@ -63,6 +121,7 @@ impl Default for Link {
             name: String::new(),
             location: Some(PathBuf::new()),
             number: None,
+            anchor: None,
             nested_items: Vec::new(),
         }
     }
@@ -100,6 +159,7 @@ struct SummaryParser<'a> {
     offset: usize,
 
     back: Option<Event<'a>>,
+    diagnostics: Vec<ParseDiagnostic>,
 }
 
 macro_rules! collect_events {
@@ -142,6 +202,7 @@ impl<'a> SummaryParser<'a> {
             stream: pulldown_parser,
             offset: 0,
             back: None,
+            diagnostics: Vec::new(),
         }
     }
 
@@ -157,25 +218,50 @@ impl<'a> SummaryParser<'a> {
     }
 
     /// Parse the text the `SummaryParser` was created with.
-    fn parse(mut self) -> Result<Summary> {
+    fn parse(mut self) -> Result<(Summary, Vec<ParseDiagnostic>)> {
         let title = self.parse_title();
 
         let prefix_chapters = self
             .parse_affix(true)
             .with_context(|| "There was an error parsing the prefix chapters")?;
-        let numbered_chapters = self
+        let parts = self
             .parse_parts()
             .with_context(|| "There was an error parsing the numbered chapters")?;
         let suffix_chapters = self
             .parse_affix(false)
             .with_context(|| "There was an error parsing the suffix chapters")?;
 
-        Ok(Summary {
+        let summary = Summary {
             title,
             prefix_chapters,
-            numbered_chapters,
+            parts,
             suffix_chapters,
-        })
+        };
+
+        Ok((summary, self.diagnostics))
+    }
+
+    /// Record a recoverable problem at the parser's current position.
+    fn push_diagnostic(&mut self, message: impl Into<String>, severity: Severity) {
+        let (line, col) = self.current_location();
+        self.diagnostics.push(ParseDiagnostic {
+            line,
+            col,
+            message: message.into(),
+            severity,
+        });
+    }
+
+    /// Skip events until the end of the current list item (or the stream
+    /// ends), so a malformed item doesn't desynchronise the rest of the
+    /// parse.
+    fn skip_to_item_end(&mut self) {
+        loop {
+            match self.next_event() {
+                Some(Event::End(Tag::Item)) | None => break,
+                Some(_) => {}
+            }
+        }
     }
 
     /// Parse the affix chapters.
@@ -212,7 +298,7 @@ impl<'a> SummaryParser<'a> {
         Ok(items)
     }
 
-    fn parse_parts(&mut self) -> Result<Vec<SummaryItem>> {
+    fn parse_parts(&mut self) -> Result<Vec<Part>> {
         let mut parts = vec![];
 
         // We want the section numbers to be continues through all parts.
@@ -243,15 +329,18 @@ impl<'a> SummaryParser<'a> {
                 None => break, // EOF, bail...
             };
 
-            // Parse the rest of the part.
+            // Parse the rest of the part, remembering where its numbering
+            // started so `Part::start_number` doesn't need to re-derive it.
+            let start_number = SectionNumber(vec![root_items + 1]);
             let numbered_chapters = self
                 .parse_numbered(&mut root_items, &mut root_number)
                 .with_context(|| "There was an error parsing the numbered chapters")?;
 
-            if let Some(title) = title {
-                parts.push(SummaryItem::PartTitle(title));
-            }
-            parts.extend(numbered_chapters);
+            parts.push(Part {
+                title,
+                numbered_chapters,
+                start_number,
+            });
         }
 
         Ok(parts)
@@ -263,16 +352,13 @@ impl<'a> SummaryParser<'a> {
         let link_content = collect_events!(self.stream, end Tag::Link(..));
         let name = stringify_events(link_content);
 
-        let path = if href.is_empty() {
-            None
-        } else {
-            Some(PathBuf::from(href))
-        };
+        let (path, anchor) = split_href_anchor(href);
 
         Link {
             name,
             location: path,
             number: None,
+            anchor,
             nested_items: Vec::new(),
         }
     }
@@ -374,8 +460,9 @@ impl<'a> SummaryParser<'a> {
         loop {
             match self.next_event() {
                 Some(Event::Start(Tag::Item)) => {
-                    let item = self.parse_nested_item(parent, items.len())?;
-                    items.push(item);
+                    if let Some(item) = self.parse_nested_item(parent, items.len())? {
+                        items.push(item);
+                    }
                 }
                 Some(Event::Start(Tag::List(..))) => {
                     // Skip this tag after comment because it is not nested.
@@ -402,11 +489,14 @@ impl<'a> SummaryParser<'a> {
         Ok(items)
     }
 
+    /// Parse a single nested list item. Returns `Ok(None)` (after recording a
+    /// diagnostic) for a malformed item, rather than aborting the whole
+    /// parse, so the rest of `SUMMARY.md` can still be checked in one pass.
     fn parse_nested_item(
         &mut self,
         parent: &SectionNumber,
         num_existing_items: usize,
-    ) -> Result<SummaryItem> {
+    ) -> Result<Option<SummaryItem>> {
         loop {
             match self.next_event() {
                 Some(Event::Start(Tag::Paragraph)) => continue,
@@ -427,13 +517,17 @@ impl<'a> SummaryParser<'a> {
 
                     link.number = Some(number);
 
-                    return Ok(SummaryItem::Link(link));
+                    return Ok(Some(SummaryItem::Link(link)));
                 }
+                None => return Ok(None),
                 other => {
                     warn!("Expected a start of a link, actually got {:?}", other);
-                    bail!(self.parse_error(
-                        "The link items for nested chapters must only contain a hyperlink"
-                    ));
+                    self.push_diagnostic(
+                        "The link items for nested chapters must only contain a hyperlink",
+                        Severity::Error,
+                    );
+                    self.skip_to_item_end();
+                    return Ok(None);
                 }
             }
         }
@@ -472,6 +566,20 @@ impl<'a> SummaryParser<'a> {
     }
 }
 
+/// Split a `SUMMARY.md` link's href into its file location and `#fragment`
+/// anchor, if any.
+fn split_href_anchor(href: String) -> (Option<PathBuf>, Option<String>) {
+    if href.is_empty() {
+        return (None, None);
+    }
+
+    match href.split_once('#') {
+        Some((path, frag)) if path.is_empty() => (None, Some(frag.to_string())),
+        Some((path, frag)) => (Some(PathBuf::from(path)), Some(frag.to_string())),
+        None => (Some(PathBuf::from(href)), None),
+    }
+}
+
 fn update_section_numbers(sections: &mut [SummaryItem], level: usize, by: u32) {
     for section in sections {
         if let SummaryItem::Link(ref mut link) = *section {
@@ -1,11 +1,67 @@
-use crate::get_files_dir;
+use clap::{arg, ArgMatches, Command, ValueEnum};
 use fpm_importer::errors::Result;
-use fpm_importer::MDBook;
-
-pub fn execute(dir_name: &str) -> Result<()> {
-    let book_dir = get_files_dir(dir_name);
-    dbg!(&book_dir);
-    let book = MDBook::load(&book_dir)?;
-    book.build()?;
-    Ok(())
+use fpm_importer::renderer::{HtmlHandlebars, MarkdownRenderer};
+use fpm_importer::{MDBook, Renderer};
+use std::path::PathBuf;
+
+use super::{apply_config_overrides, config_arg, get_book_dir};
+
+/// A bundled rendering backend `build` can be pinned to with `--engine`,
+/// bypassing `[output]`-driven renderer selection entirely.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Engine {
+    /// The legacy HTML/Handlebars renderer, registered as `html`.
+    Html,
+    /// The FTD-targeted renderer, registered as `markdown`.
+    Markdown,
+}
+
+impl Engine {
+    fn renderer(self) -> Box<dyn Renderer> {
+        match self {
+            Engine::Html => Box::new(HtmlHandlebars::new()),
+            Engine::Markdown => Box::new(MarkdownRenderer::new()),
+        }
+    }
+}
+
+/// The `build` subcommand.
+pub fn make_subcommand() -> Command {
+    Command::new("build")
+        .about("Builds a book from its markdown files")
+        .arg(
+            arg!(-d --"dest-dir" <dest_dir>
+                "Output directory for the book\n\
+                Relative paths are interpreted relative to the book's root directory.\n\
+                If omitted, `build.build-dir` from `book.toml` is used.")
+            .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(arg!([dir]
+            "Root directory for the book\n\
+            (Defaults to the Current Directory when omitted)"
+        ))
+        .arg(config_arg())
+        .arg(
+            arg!(-m --engine <engine>
+                "Render with a single bundled engine instead of the renderers configured in `[output]`")
+            .required(false)
+            .value_parser(clap::value_parser!(Engine)),
+        )
+}
+
+/// The `build` command implementation.
+pub fn execute(args: &ArgMatches) -> Result<()> {
+    let book_dir = get_book_dir(args);
+    let mut book = MDBook::load(&book_dir)?;
+
+    apply_config_overrides(&mut book.config, args)?;
+
+    if let Some(dest_dir) = args.get_one::<PathBuf>("dest-dir") {
+        book.config.build.build_dir = dest_dir.clone();
+    }
+
+    match args.get_one::<Engine>("engine") {
+        Some(engine) => book.execute_build_process(engine.renderer().as_ref()),
+        None => book.build(),
+    }
 }
@@ -0,0 +1,23 @@
+use clap::{arg, ArgMatches, Command};
+use clap_complete::{generate, Shell};
+use fpm_importer::errors::Result;
+use std::io;
+
+/// The `completions` subcommand.
+pub fn make_subcommand() -> Command {
+    Command::new("completions")
+        .about("Generates a shell completion script on stdout")
+        .arg(arg!(--shell <shell> "The shell to generate completions for").value_parser(clap::value_parser!(Shell)))
+}
+
+/// The `completions` command implementation.
+///
+/// `command` is regenerated by the caller (rather than reusing the one that
+/// was already parsed) since `clap_complete::generate` needs to walk the
+/// full `Command` tree, including the `completions` subcommand itself.
+pub fn execute(mut command: Command, args: &ArgMatches) -> Result<()> {
+    let shell = *args.get_one::<Shell>("shell").unwrap();
+    let name = command.get_name().to_string();
+    generate(shell, &mut command, name, &mut io::stdout());
+    Ok(())
+}
@@ -0,0 +1,75 @@
+use anyhow::bail;
+use clap::{arg, ArgMatches, Command};
+use fpm_importer::errors::Result;
+use fpm_importer::utils::syntax;
+use fpm_importer::MDBook;
+use std::path::PathBuf;
+
+use super::{apply_config_overrides, config_arg, get_book_dir};
+
+/// The `gen-syntax-cache` subcommand.
+pub fn make_subcommand() -> Command {
+    Command::new("gen-syntax-cache")
+        .about(
+            "Precomputes a syntect SyntaxSet and per-theme class-based CSS, \
+            so `build` can highlight code server-side without paying the \
+            syntax-loading cost on every run",
+        )
+        .arg(
+            arg!(-d --"dest-dir" <dest_dir>
+                "Directory to write `syntaxes.bin` and `css/syntax/*.css` into\n\
+                Relative paths are interpreted relative to the book's root directory.\n\
+                If omitted, `build.build-dir` from `book.toml` is used.")
+            .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(arg!([dir]
+            "Root directory for the book\n\
+            (Defaults to the Current Directory when omitted)"
+        ))
+        .arg(config_arg())
+        .arg(
+            arg!(--"syntaxes-only" "Only write `syntaxes.bin`, skipping the theme CSS dump")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            arg!(--"themes-only" "Only write the theme CSS files, skipping `syntaxes.bin`")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            arg!(--"no-default-syntaxes"
+                "Drop Sublime's bundled open-source syntaxes, using only the \
+                `.sublime-syntax` files found in the book's source directory")
+            .action(clap::ArgAction::SetTrue),
+        )
+}
+
+/// The `gen-syntax-cache` command implementation.
+pub fn execute(args: &ArgMatches) -> Result<()> {
+    let book_dir = get_book_dir(args);
+    let mut book = MDBook::load(&book_dir)?;
+
+    apply_config_overrides(&mut book.config, args)?;
+
+    if let Some(dest_dir) = args.get_one::<PathBuf>("dest-dir") {
+        book.config.build.build_dir = dest_dir.clone();
+    }
+    let dest_dir = book.root.join(&book.config.build.build_dir);
+
+    let syntaxes_only = args.get_flag("syntaxes-only");
+    let themes_only = args.get_flag("themes-only");
+    if syntaxes_only && themes_only {
+        bail!("--syntaxes-only and --themes-only are mutually exclusive");
+    }
+
+    if !themes_only {
+        let no_default_syntaxes = args.get_flag("no-default-syntaxes");
+        let syntax_set = syntax::build_syntax_set(&book.source_dir(), no_default_syntaxes)?;
+        syntax::write_syntax_cache(&syntax_set, &dest_dir)?;
+    }
+
+    if !syntaxes_only {
+        syntax::write_theme_css(&dest_dir)?;
+    }
+
+    Ok(())
+}
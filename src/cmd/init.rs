@@ -0,0 +1,64 @@
+use super::get_book_dir;
+use clap::{arg, ArgMatches, Command};
+use fpm_importer::errors::*;
+use fpm_importer::theme::Theme;
+use fpm_importer::utils;
+use fpm_importer::Config;
+use log::info;
+
+const SUMMARY_MD: &str = "# Summary\n\n- [Chapter 1](./chapter_1.md)\n";
+const CHAPTER_1_MD: &str = "# Chapter 1\n";
+
+/// The `init` subcommand.
+pub fn make_subcommand() -> Command {
+    Command::new("init")
+        .about("Creates the boilerplate structure and files for a new book")
+        .arg(arg!([dir]
+            "Directory to create the book in\n\
+            (Defaults to the Current Directory when omitted)"
+        ))
+        .arg(arg!(--title <title> "Sets the book's title").required(false))
+        .arg(arg!(--ignore "Creates a `.gitignore` to exclude the build directory from version control"))
+        .arg(arg!(--theme "Copies the default theme assets into the book's theme directory"))
+}
+
+/// The `init` command implementation.
+pub fn execute(args: &ArgMatches) -> Result<()> {
+    let book_dir = get_book_dir(args);
+
+    let mut config = Config::default();
+    if let Some(title) = args.get_one::<String>("title") {
+        config.book.title = Some(title.clone());
+    }
+
+    let book_toml =
+        toml::to_string(&config).with_context(|| "Unable to serialize the default `book.toml`")?;
+    utils::fs::write_file(&book_dir, "book.toml", book_toml.as_bytes())?;
+
+    utils::fs::write_file(
+        &book_dir.join(&config.book.src),
+        "SUMMARY.md",
+        SUMMARY_MD.as_bytes(),
+    )?;
+    utils::fs::write_file(
+        &book_dir.join(&config.book.src),
+        "chapter_1.md",
+        CHAPTER_1_MD.as_bytes(),
+    )?;
+
+    if args.get_flag("ignore") {
+        let gitignore = format!("/{}\n", config.build.build_dir.display());
+        utils::fs::write_file(&book_dir, ".gitignore", gitignore.as_bytes())?;
+    }
+
+    if args.get_flag("theme") {
+        let theme_dir = config.build.theme_dir(&book_dir);
+        let theme = Theme::default();
+        utils::fs::write_file(&theme_dir, "index.hbs", &theme.index)?;
+        utils::fs::write_file(&theme_dir, "head.hbs", &theme.head)?;
+        utils::fs::write_file(&theme_dir, "theme.css", &theme.css)?;
+    }
+
+    info!("Book successfully initialized in {:?}", book_dir);
+    Ok(())
+}
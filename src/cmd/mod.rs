@@ -0,0 +1,65 @@
+use clap::{arg, Arg, ArgMatches};
+use fpm_importer::errors::*;
+use fpm_importer::Config;
+use std::env;
+use std::path::PathBuf;
+
+pub mod build;
+pub mod completions;
+pub mod gen_syntax_cache;
+pub mod init;
+pub mod remove_hidden_lines;
+pub mod serve;
+pub mod watch;
+
+/// Get the book directory from the command-line arguments, relative to the
+/// current directory if it was given as a relative path.
+pub fn get_book_dir(args: &ArgMatches) -> PathBuf {
+    if let Some(p) = args.get_one::<PathBuf>("dir") {
+        // Check if path is relative from current dir, or absolute...
+        if p.is_relative() {
+            env::current_dir().unwrap().join(p)
+        } else {
+            p.to_path_buf()
+        }
+    } else {
+        env::current_dir().expect("Unable to determine the current directory")
+    }
+}
+
+/// A repeatable `-c key=value` argument for overriding `book.toml` settings
+/// from the command line, shared by every subcommand that loads a book.
+pub fn config_arg() -> Arg {
+    arg!(-c --config <"key=value"> "Override a configuration value (can be used multiple times)")
+        .action(clap::ArgAction::Append)
+}
+
+/// Apply every `-c key=value` passed on the command line to `config`, in the
+/// order they were given. `value` is parsed as TOML where possible (so
+/// `-c output.html.curly-quotes=true` sets a bool, not the string `"true"`),
+/// falling back to a plain string otherwise.
+pub fn apply_config_overrides(config: &mut Config, args: &ArgMatches) -> Result<()> {
+    let Some(overrides) = args.get_many::<String>("config") else {
+        return Ok(());
+    };
+
+    for entry in overrides {
+        let (key, value) = entry
+            .split_once('=')
+            .with_context(|| format!("Invalid `-c` override {:?}, expected `key=value`", entry))?;
+
+        let value = parse_toml_value(value);
+        config.set(key, value)?;
+    }
+
+    Ok(())
+}
+
+/// Parse a CLI override's right-hand side as a standalone TOML value,
+/// falling back to treating it as a plain string when it doesn't parse.
+fn parse_toml_value(raw: &str) -> toml::Value {
+    toml::from_str::<toml::value::Table>(&format!("value = {}", raw))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(raw.to_string()))
+}
@@ -0,0 +1,75 @@
+use clap::{arg, ArgMatches, Command};
+use fpm_importer::errors::*;
+use fpm_importer::renderer::strip_hidden_lines;
+use fpm_importer::MDBook;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{apply_config_overrides, config_arg, get_book_dir};
+
+/// The `remove-hidden-lines` subcommand.
+pub fn make_subcommand() -> Command {
+    Command::new("remove-hidden-lines")
+        .about(
+            "Rewrites a previously-built book's chapters so code blocks hold \
+            their plain, de-marked source (hidden lines are kept as ordinary \
+            lines), for publishing to print/no-JS targets where the \
+            `boring`-line toggle can't run",
+        )
+        .arg(
+            arg!(-d --"dest-dir" <dest_dir>
+                "Directory of an already-built book to rewrite in place\n\
+                Relative paths are interpreted relative to the book's root directory.\n\
+                If omitted, `build.build-dir` from `book.toml` is used.")
+            .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(arg!([dir]
+            "Root directory for the book\n\
+            (Defaults to the Current Directory when omitted)"
+        ))
+        .arg(config_arg())
+}
+
+/// The `remove-hidden-lines` command implementation.
+pub fn execute(args: &ArgMatches) -> Result<()> {
+    let book_dir = get_book_dir(args);
+    let mut book = MDBook::load(&book_dir)?;
+
+    apply_config_overrides(&mut book.config, args)?;
+
+    if let Some(dest_dir) = args.get_one::<PathBuf>("dest-dir") {
+        book.config.build.build_dir = dest_dir.clone();
+    }
+    let dest_dir = book.root.join(&book.config.build.build_dir);
+
+    strip_hidden_lines_in_dir(&dest_dir)
+}
+
+/// Recursively rewrites every UTF-8 file under `dir` in place, replacing
+/// marked-up code blocks with their plain source (see
+/// [`strip_hidden_lines`]). Files that aren't valid UTF-8 (images, fonts,
+/// the syntax cache, ...) are left untouched.
+fn strip_hidden_lines_in_dir(dir: &Path) -> Result<()> {
+    for entry in fs::read_dir(dir).with_context(|| format!("Unable to read {}", dir.display()))? {
+        let entry = entry?;
+        let path = entry.path();
+        let metadata = entry
+            .metadata()
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+
+        if metadata.is_dir() {
+            strip_hidden_lines_in_dir(&path)?;
+        } else if metadata.is_file() {
+            let Ok(content) = fs::read_to_string(&path) else {
+                continue;
+            };
+            let stripped = strip_hidden_lines(&content);
+            if stripped != content {
+                fs::write(&path, stripped)
+                    .with_context(|| format!("Unable to write {}", path.display()))?;
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,165 @@
+use super::{apply_config_overrides, config_arg, get_book_dir, watch};
+use clap::{arg, ArgMatches, Command};
+use fpm_importer::errors::{Error, Result};
+use fpm_importer::utils;
+use fpm_importer::MDBook;
+use futures_util::{SinkExt, StreamExt};
+use log::{error, info};
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::path::PathBuf;
+use tokio::sync::broadcast;
+use warp::ws::Message;
+use warp::{Filter, Reply};
+
+/// The script injected into every served HTML page, just before `</body>`,
+/// so the browser reconnects to `/__livereload` and reloads on rebuild.
+const LIVERELOAD_SCRIPT: &str = r#"<script>
+(function () {
+    const socket = new WebSocket((location.protocol === "https:" ? "wss://" : "ws://") + location.host + "/__livereload");
+    socket.onmessage = () => location.reload();
+    socket.onclose = () => setTimeout(() => location.reload(), 1000);
+})();
+</script>"#;
+
+const DEFAULT_HOSTNAME: &str = "localhost";
+const DEFAULT_PORT: &str = "3000";
+
+/// The `serve` subcommand.
+pub fn make_subcommand() -> Command {
+    Command::new("serve")
+        .about("Serves a book at http://<hostname>:<port>, rebuilding it on changes")
+        .arg(
+            arg!(-d --"dest-dir" <dest_dir>
+                "Output directory for the book\n\
+                Relative paths are interpreted relative to the book's root directory.\n\
+                If omitted, `build.build-dir` from `book.toml` is used.")
+            .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(arg!([dir]
+            "Root directory for the book\n\
+            (Defaults to the Current Directory when omitted)"
+        ))
+        .arg(arg!(-p --port <port> "Use another port").default_value(DEFAULT_PORT))
+        .arg(arg!(-n --hostname <hostname> "Use another hostname").default_value(DEFAULT_HOSTNAME))
+        .arg(arg!(-o --open "Opens the served book in a web browser"))
+        .arg(config_arg())
+}
+
+/// The `serve` command implementation.
+pub fn execute(args: &ArgMatches) -> Result<()> {
+    let book_dir = get_book_dir(args);
+    let mut book = MDBook::load(&book_dir)?;
+
+    apply_config_overrides(&mut book.config, args)?;
+
+    if let Some(dest_dir) = args.get_one::<PathBuf>("dest-dir") {
+        book.config.build.build_dir = dest_dir.clone();
+    }
+
+    let port = args.get_one::<String>("port").unwrap().clone();
+    let hostname = args.get_one::<String>("hostname").unwrap().clone();
+    let open_browser = args.get_flag("open");
+
+    let address = format!("{}:{}", hostname, port);
+    let sockaddr: SocketAddr = address
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| Error::msg(format!("no address found for {}", address)))?;
+
+    book.build()?;
+    let build_dir = book.build_dir_for("html");
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async move {
+        let (reload_tx, _) = broadcast::channel::<()>(1);
+        let broadcast_tx = reload_tx.clone();
+
+        let livereload = warp::path("__livereload")
+            .and(warp::ws())
+            .map(move |ws: warp::ws::Ws| {
+                let mut rx = broadcast_tx.subscribe();
+                ws.on_upgrade(move |websocket| async move {
+                    let (mut tx, _rx) = websocket.split();
+                    while rx.recv().await.is_ok() {
+                        if tx.send(Message::text("reload")).await.is_err() {
+                            break;
+                        }
+                    }
+                })
+            });
+
+        let serve_dir = build_dir.clone();
+        let files = warp::path::tail().and_then(move |tail: warp::path::Tail| {
+            serve_file(serve_dir.clone(), tail.as_str().to_string())
+        });
+
+        let routes = livereload.or(files);
+
+        info!("Serving on http://{}", address);
+        if open_browser {
+            let _ = webbrowser::open(&format!("http://{}", address));
+        }
+
+        let config = book.config.clone();
+        tokio::task::spawn_blocking(move || {
+            watch::trigger_on_change(&book, move |paths, book_dir| {
+                info!("Files changed: {:?}\nBuilding book...", paths);
+                let result =
+                    MDBook::load_with_config(book_dir, config.clone()).and_then(|b| b.build());
+
+                match result {
+                    Ok(()) => {
+                        let _ = reload_tx.send(());
+                    }
+                    Err(e) => {
+                        error!("Unable to build the book");
+                        utils::log_backtrace(&e);
+                    }
+                }
+            });
+        });
+
+        warp::serve(routes).run(sockaddr).await;
+    });
+
+    Ok(())
+}
+
+/// Read `<build_dir>/<relative_path>` (defaulting to `index.html`) and, for
+/// `.html` files, splice [`LIVERELOAD_SCRIPT`] in just before `</body>`.
+async fn serve_file(
+    build_dir: PathBuf,
+    relative_path: String,
+) -> std::result::Result<impl warp::Reply, warp::Rejection> {
+    let relative_path = if relative_path.is_empty() {
+        "index.html".to_string()
+    } else {
+        relative_path
+    };
+    let file_path = build_dir.join(&relative_path);
+
+    let canonical_build_dir = tokio::fs::canonicalize(&build_dir)
+        .await
+        .map_err(|_| warp::reject::not_found())?;
+    let canonical_file_path = tokio::fs::canonicalize(&file_path)
+        .await
+        .map_err(|_| warp::reject::not_found())?;
+    if !canonical_file_path.starts_with(&canonical_build_dir) {
+        return Err(warp::reject::not_found());
+    }
+
+    let bytes = tokio::fs::read(&canonical_file_path)
+        .await
+        .map_err(|_| warp::reject::not_found())?;
+
+    if canonical_file_path.extension().and_then(|ext| ext.to_str()) == Some("html") {
+        let mut html = String::from_utf8_lossy(&bytes).into_owned();
+        match html.rfind("</body>") {
+            Some(idx) => html.insert_str(idx, LIVERELOAD_SCRIPT),
+            None => html.push_str(LIVERELOAD_SCRIPT),
+        }
+        Ok(warp::reply::html(html).into_response())
+    } else {
+        Ok(warp::reply::Response::new(bytes.into()))
+    }
+}
@@ -0,0 +1,111 @@
+use super::{apply_config_overrides, config_arg, get_book_dir};
+use clap::{arg, ArgMatches, Command};
+use fpm_importer::errors::Result;
+use fpm_importer::utils;
+use fpm_importer::MDBook;
+use log::{error, info, warn};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::channel;
+use std::thread::sleep;
+use std::time::Duration;
+
+/// How long to wait after the first filesystem event before rebuilding, so a
+/// burst of saves (editors that write several files per save) only triggers
+/// one rebuild.
+const DEBOUNCE: Duration = Duration::from_millis(50);
+
+/// The `watch` subcommand.
+pub fn make_subcommand() -> Command {
+    Command::new("watch")
+        .about("Watches a book's files and rebuilds it on changes")
+        .arg(
+            arg!(-d --"dest-dir" <dest_dir>
+                "Output directory for the book\n\
+                Relative paths are interpreted relative to the book's root directory.\n\
+                If omitted, `build.build-dir` from `book.toml` is used.")
+            .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(arg!([dir]
+            "Root directory for the book\n\
+            (Defaults to the Current Directory when omitted)"
+        ))
+        .arg(config_arg())
+}
+
+/// The `watch` command implementation.
+pub fn execute(args: &ArgMatches) -> Result<()> {
+    let book_dir = get_book_dir(args);
+    let mut book = MDBook::load(&book_dir)?;
+
+    apply_config_overrides(&mut book.config, args)?;
+
+    if let Some(dest_dir) = args.get_one::<PathBuf>("dest-dir") {
+        book.config.build.build_dir = dest_dir.clone();
+    }
+
+    book.build()?;
+
+    trigger_on_change(&book, |paths, book_dir| {
+        info!("Files changed: {:?}\nBuilding book...", paths);
+        let result = MDBook::load(book_dir).and_then(|b| b.build());
+
+        if let Err(e) = result {
+            error!("Unable to build the book");
+            utils::log_backtrace(&e);
+        }
+    });
+
+    Ok(())
+}
+
+/// Watch the book's source and theme directories (plus any
+/// `build.extra-watch-dirs`) and call `closure` with the set of changed
+/// paths, debounced, every time something changes. Never returns.
+pub fn trigger_on_change<F>(book: &MDBook, closure: F)
+where
+    F: Fn(&[PathBuf], &Path),
+{
+    let (tx, rx) = channel();
+
+    let mut watcher: RecommendedWatcher = match notify::recommended_watcher(tx) {
+        Ok(w) => w,
+        Err(e) => {
+            error!("Error while trying to watch the files:\n\n\t{:?}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if let Err(e) = watcher.watch(&book.source_dir(), RecursiveMode::Recursive) {
+        error!("Error while watching {:?}:\n    {:?}", book.source_dir(), e);
+        std::process::exit(1);
+    }
+
+    let _ = watcher.watch(&book.theme_dir(), RecursiveMode::Recursive);
+
+    for dir in &book.config.build.extra_watch_dirs {
+        if let Err(e) = watcher.watch(dir, RecursiveMode::Recursive) {
+            warn!("Error while watching {:?}:\n    {:?}", dir, e);
+        }
+    }
+
+    info!("Listening for changes...");
+
+    loop {
+        let Ok(first_event) = rx.recv() else {
+            break;
+        };
+        sleep(DEBOUNCE);
+        let other_events = rx.try_iter();
+
+        let paths: Vec<PathBuf> = std::iter::once(first_event)
+            .chain(other_events)
+            .filter_map(|event| event.ok())
+            .flat_map(|event| event.paths)
+            .collect();
+
+        if !paths.is_empty() {
+            closure(&paths, &book.root);
+        }
+    }
+}
@@ -1,8 +1,10 @@
 
-use log::{debug, trace};
+use log::{debug, trace, warn};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::env;
+use std::fmt;
 use std::fs::File;
 use std::io::Read;
 use std::path::{Path, PathBuf};
@@ -23,6 +25,9 @@ pub struct Config {
     pub build: BuildConfig,
     /// Information about Rust language support.
     pub rust: RustConfig,
+    /// The FPM package identity and dependencies used when generating
+    /// `FPM.ftd` for the rendered output.
+    pub fpm: FpmConfig,
     rest: Value,
 }
 
@@ -47,29 +52,49 @@ impl Config {
         Config::from_str(&buffer)
     }
 
+    /// Override config keys from `FTD_`-prefixed environment variables. See
+    /// [`Config::update_from_env_with_prefix`] for the key translation rules.
     pub fn update_from_env(&mut self) {
-        debug!("Updating the config from environment variables");
+        self.update_from_env_with_prefix("ftd");
+    }
+
+    /// Override config keys from environment variables starting with
+    /// `<prefix>_`, so embedders can namespace overrides instead of the
+    /// hard-coded `FTD_` prefix.
+    ///
+    /// A variable name has its prefix stripped, is lowercased, `__` becomes
+    /// `.` (section separator) and `_` becomes `-` (so it lines up with the
+    /// `kebab-case` keys `book.toml` uses), then its value is parsed as
+    /// JSON, falling back to a plain string if that fails. Nested JSON
+    /// objects are expanded recursively into further dotted path segments,
+    /// so `FTD_OUTPUT__HTML__SEARCH__BOOST_TITLE=3` and
+    /// `FTD_OUTPUT__HTML__ADDITIONAL_CSS='["a.css","b.css"]'` both work,
+    /// setting a nested scalar and a vector respectively.
+    pub fn update_from_env_with_prefix(&mut self, prefix: &str) {
+        debug!("Updating the config from {:?}_-prefixed environment variables", prefix);
 
-        let overrides =
-            env::vars().filter_map(|(key, value)| parse_env(&key).map(|index| (index, value)));
+        let overrides = env::vars()
+            .filter_map(|(key, value)| parse_env(prefix, &key).map(|index| (index, value)));
 
         for (key, value) in overrides {
             trace!("{} => {}", key, value);
             let parsed_value = serde_json::from_str(&value)
                 .unwrap_or_else(|_| serde_json::Value::String(value.to_string()));
 
-            if key == "ftd_output" || key == "build" {
-                if let serde_json::Value::Object(ref map) = parsed_value {
-                    // To `set` each `key`, we wrap them as `prefix.key`
-                    for (k, v) in map {
-                        let full_key = format!("{}.{}", key, k);
-                        self.set(&full_key, v).expect("unreachable");
-                    }
-                    return;
-                }
-            }
+            self.apply_env_override(&key, parsed_value);
+        }
+    }
 
-            self.set(key, parsed_value).expect("unreachable");
+    /// Set `key` to `value`, expanding nested JSON objects into further
+    /// dotted path segments (`key.inner_key`) instead of serializing them as
+    /// a single opaque table value.
+    fn apply_env_override(&mut self, key: &str, value: serde_json::Value) {
+        if let serde_json::Value::Object(map) = value {
+            for (sub_key, sub_value) in map {
+                self.apply_env_override(&format!("{}.{}", key, sub_key), sub_value);
+            }
+        } else {
+            self.set(key, value).expect("unreachable");
         }
     }
 
@@ -157,6 +182,181 @@ impl Config {
         self.get(&key).and_then(Value::as_table)
     }
 
+    /// Return the configured preprocessor names in the order they should
+    /// run, honoring each `[preprocessor.<name>]`'s `before`/`after` arrays.
+    ///
+    /// This is a read-only introspection surface for embedders/tooling that
+    /// want to display or validate the effective pipeline without running a
+    /// build. It is intentionally stricter than the internal ordering
+    /// `MDBook::build` actually uses (which only warns on an unresolved
+    /// `before`/`after` name, so preprocessors can be toggled on and off
+    /// freely): this errors instead, so a caller that wants to *validate* a
+    /// `book.toml` catches a typo'd name.
+    pub fn preprocessor_order(&self) -> Result<Vec<String>> {
+        match self.get("preprocessor").and_then(Value::as_table) {
+            Some(table) => topological_order("preprocessor", table),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Return the configured renderer names in the order they should run,
+    /// honoring each `[output.<name>]`'s `before`/`after` arrays. See
+    /// [`Config::preprocessor_order`] for how this differs from the
+    /// internal build-time ordering.
+    pub fn renderer_order(&self) -> Result<Vec<String>> {
+        match self.get("output").and_then(Value::as_table) {
+            Some(table) => topological_order("output", table),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Get the `[build.languages.<code>]` entry for `code`, if any.
+    pub fn language(&self, code: &str) -> Option<&LanguageConfig> {
+        self.build.languages.get(code)
+    }
+
+    /// The book's default language, taken from `book.language`.
+    pub fn default_language(&self) -> Option<&str> {
+        self.book.language.as_deref()
+    }
+
+    /// Build the effective `BookConfig` for `code`, layering its
+    /// `[build.languages.<code>]` entry (if any) over the base `book` table.
+    /// `book.src` is rewritten to the translation's source directory: the
+    /// entry's own `src` override if set, otherwise `book.src/<code>`.
+    pub fn effective_book_config(&self, code: &str) -> BookConfig {
+        let mut book = self.book.clone();
+        book.language = Some(code.to_string());
+        book.src = self.book.src.join(code);
+
+        if let Some(entry) = self.language(code) {
+            if let Some(title) = &entry.title {
+                book.title = Some(title.clone());
+            }
+            if let Some(description) = &entry.description {
+                book.description = Some(description.clone());
+            }
+            if let Some(authors) = &entry.authors {
+                book.authors = authors.clone();
+            }
+            if let Some(src) = &entry.src {
+                book.src = self.book.src.join(src);
+            }
+        }
+
+        book
+    }
+
+    /// Check the opaque parts of the config (`rest`) for keys that don't
+    /// belong to any known field, or whose value doesn't deserialize into
+    /// its target field's type. Limited to the tables this crate owns the
+    /// schema of: `[ftd_output]`, `[build]`, `[rust]` and `[output.html]`.
+    /// Any other `[preprocessor.*]`/`[output.*]` table belongs to a
+    /// third-party plugin this crate knows nothing about, and is left
+    /// alone.
+    pub fn validate(&self) -> Vec<ConfigWarning> {
+        let mut warnings = Vec::new();
+
+        if let Some(book) = self.get("ftd_output").and_then(Value::as_table) {
+            check_table::<BookConfig>("ftd_output", book, &mut warnings);
+        }
+        if let Some(build) = self.get("build").and_then(Value::as_table) {
+            check_table::<BuildConfig>("build", build, &mut warnings);
+        }
+        if let Some(rust) = self.get("rust").and_then(Value::as_table) {
+            check_table::<RustConfig>("rust", rust, &mut warnings);
+        }
+        if let Some(html) = self.get("output.html").and_then(Value::as_table) {
+            check_table::<HtmlConfig>("output.html", html, &mut warnings);
+        }
+
+        warnings
+    }
+
+    /// Detect an old-style, flat `book.toml` (one predating the
+    /// `[ftd_output]`/`[build]`/`[rust]`/`[output]` section tables) and
+    /// rewrite it into the current layout, so existing projects keep
+    /// loading instead of silently losing their settings.
+    fn from_legacy(mut table: Table) -> Table {
+        const LEGACY_KEYS: &[&str] = &[
+            "title",
+            "author",
+            "authors",
+            "description",
+            "source",
+            "src",
+            "dest",
+            "destination",
+            "theme_path",
+            "google_analytics",
+            "multilingual",
+        ];
+
+        let has_current_sections = ["ftd_output", "build", "rust", "output"]
+            .iter()
+            .any(|key| table.contains_key(*key));
+        let is_legacy =
+            !has_current_sections && LEGACY_KEYS.iter().any(|key| table.contains_key(*key));
+
+        if !is_legacy {
+            return table;
+        }
+
+        let mut book = Table::new();
+        let mut build = Table::new();
+        let mut output_html = Table::new();
+
+        if let Some(value) = table.remove("title") {
+            warn!("`title` is deprecated, use `ftd_output.title` instead");
+            book.insert("title".to_string(), value);
+        }
+        if let Some(value) = table.remove("author").or_else(|| table.remove("authors")) {
+            warn!("`author`/`authors` is deprecated, use `ftd_output.authors` instead");
+            let value = match value {
+                Value::Array(_) => value,
+                other => Value::Array(vec![other]),
+            };
+            book.insert("authors".to_string(), value);
+        }
+        if let Some(value) = table.remove("description") {
+            warn!("`description` is deprecated, use `ftd_output.description` instead");
+            book.insert("description".to_string(), value);
+        }
+        if let Some(value) = table.remove("source").or_else(|| table.remove("src")) {
+            warn!("`source`/`src` is deprecated, use `ftd_output.src` instead");
+            book.insert("src".to_string(), value);
+        }
+        if let Some(value) = table.remove("dest").or_else(|| table.remove("destination")) {
+            warn!("`dest`/`destination` is deprecated, use `build.build-dir` instead");
+            build.insert("build-dir".to_string(), value);
+        }
+        if let Some(value) = table.remove("multilingual") {
+            warn!("`multilingual` is deprecated, use `ftd_output.multilingual` instead");
+            book.insert("multilingual".to_string(), value);
+        }
+        if let Some(value) = table.remove("theme_path") {
+            warn!("`theme_path` is deprecated, use `output.html.theme` instead");
+            output_html.insert("theme".to_string(), value);
+        }
+        if let Some(value) = table.remove("google_analytics") {
+            warn!("`google_analytics` is deprecated, use `output.html.google-analytics` instead");
+            output_html.insert("google-analytics".to_string(), value);
+        }
+
+        if !book.is_empty() {
+            table.insert("ftd_output".to_string(), Value::Table(book));
+        }
+        if !build.is_empty() {
+            table.insert("build".to_string(), Value::Table(build));
+        }
+        if !output_html.is_empty() {
+            let mut output = Table::new();
+            output.insert("html".to_string(), Value::Table(output_html));
+            table.insert("output".to_string(), Value::Table(output));
+        }
+
+        table
+    }
 }
 
 impl Default for Config {
@@ -165,6 +365,7 @@ impl Default for Config {
             book: BookConfig::default(),
             build: BuildConfig::default(),
             rust: RustConfig::default(),
+            fpm: FpmConfig::default(),
             rest: Value::Table(Table::default()),
         }
     }
@@ -185,33 +386,240 @@ impl<'de> serde::Deserialize<'de> for Config {
             }
         };
 
+        table = Config::from_legacy(table);
+
+        // `ftd_output`/`build`/`rust` are read (not removed) so a raw copy
+        // survives in `rest` for `Config::validate` to check field names
+        // against, even though the typed `book`/`build`/`rust` fields below
+        // remain the source of truth everywhere else.
         let book: BookConfig = table
-            .remove("ftd_output")
+            .get("ftd_output")
+            .cloned()
             .map(|book| book.try_into().map_err(D::Error::custom))
             .transpose()?
             .unwrap_or_default();
 
         let build: BuildConfig = table
-            .remove("build")
+            .get("build")
+            .cloned()
             .map(|build| build.try_into().map_err(D::Error::custom))
             .transpose()?
             .unwrap_or_default();
 
         let rust: RustConfig = table
-            .remove("rust")
+            .get("rust")
+            .cloned()
             .map(|rust| rust.try_into().map_err(D::Error::custom))
             .transpose()?
             .unwrap_or_default();
 
+        let fpm: FpmConfig = table
+            .remove("fpm")
+            .map(|fpm| fpm.try_into().map_err(D::Error::custom))
+            .transpose()?
+            .unwrap_or_default();
+
+        validate_multilingual(&book, &build.languages).map_err(D::Error::custom)?;
+
         Ok(Config {
             book,
             build,
             rust,
+            fpm,
             rest: Value::Table(table),
         })
     }
 }
 
+/// When `book.multilingual` is set, check that a default language is named
+/// and declared, and that every declared language has a resolvable source
+/// subdirectory.
+fn validate_multilingual(book: &BookConfig, languages: &HashMap<String, LanguageConfig>) -> Result<()> {
+    if !book.multilingual {
+        return Ok(());
+    }
+
+    let default_language = book.language.as_deref().ok_or_else(|| {
+        Error::msg("`multilingual = true` requires `book.language` to name the default language")
+    })?;
+
+    ensure!(
+        languages.contains_key(default_language),
+        "Default language {:?} has no matching `[build.languages.{}]` entry",
+        default_language,
+        default_language
+    );
+
+    for (code, entry) in languages {
+        let src = entry.src.clone().unwrap_or_else(|| PathBuf::from(code));
+        ensure!(
+            !src.as_os_str().is_empty(),
+            "`[build.languages.{}]` has no resolvable source directory",
+            code
+        );
+    }
+
+    Ok(())
+}
+
+/// Resolve the entries of `table` (a `[preprocessor]` or `[output]` table)
+/// into a valid execution order, honoring each entry's `before`/`after`
+/// string-array keys via Kahn's algorithm. Ties are broken by the order
+/// names appear in `table`. `section` is either `"preprocessor"` or
+/// `"output"`, used to name the table in error messages.
+fn topological_order(section: &str, table: &Table) -> Result<Vec<String>> {
+    let names: Vec<&String> = table.keys().collect();
+    let index: HashMap<&str, usize> = names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (name.as_str(), i))
+        .collect();
+
+    let mut in_edges: Vec<HashSet<usize>> = vec![HashSet::new(); names.len()];
+    let mut out_edges: Vec<HashSet<usize>> = vec![HashSet::new(); names.len()];
+
+    let resolve = |section: &str, name: &str, key: &str, deps: &Value| -> Result<Vec<usize>> {
+        let deps = deps
+            .as_array()
+            .ok_or_else(|| Error::msg(format!("Expected {}.{}.{} to be an array", section, name, key)))?;
+
+        deps.iter()
+            .map(|dep| {
+                let dep = dep.as_str().ok_or_else(|| {
+                    Error::msg(format!(
+                        "Expected {}.{}.{} to contain strings",
+                        section, name, key
+                    ))
+                })?;
+                index.get(dep).copied().ok_or_else(|| {
+                    Error::msg(format!(
+                        "{}.{}.{} references {:?}, which is not configured",
+                        section, name, key, dep
+                    ))
+                })
+            })
+            .collect()
+    };
+
+    for (name, value) in table {
+        let i = index[name.as_str()];
+        let entry = value.as_table();
+
+        if let Some(after) = entry.and_then(|t| t.get("after")) {
+            for j in resolve(section, name, "after", after)? {
+                in_edges[i].insert(j);
+                out_edges[j].insert(i);
+            }
+        }
+
+        if let Some(before) = entry.and_then(|t| t.get("before")) {
+            for j in resolve(section, name, "before", before)? {
+                in_edges[j].insert(i);
+                out_edges[i].insert(j);
+            }
+        }
+    }
+
+    let mut in_degree: Vec<usize> = in_edges.iter().map(HashSet::len).collect();
+    let mut ready: VecDeque<usize> = (0..names.len()).filter(|&i| in_degree[i] == 0).collect();
+
+    let mut order = Vec::with_capacity(names.len());
+    while let Some(i) = ready.pop_front() {
+        order.push(names[i].clone());
+
+        let mut unblocked: Vec<usize> = out_edges[i].iter().copied().collect();
+        unblocked.sort_unstable();
+        for j in unblocked {
+            in_degree[j] -= 1;
+            if in_degree[j] == 0 {
+                ready.push_back(j);
+            }
+        }
+    }
+
+    if order.len() != names.len() {
+        let cycle: Vec<&str> = (0..names.len())
+            .filter(|&i| in_degree[i] > 0)
+            .map(|i| names[i].as_str())
+            .collect();
+        bail!(
+            "Cyclic before/after dependency among {}: {:?}",
+            section,
+            cycle
+        );
+    }
+
+    Ok(order)
+}
+
+/// A problem found by [`Config::validate`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ConfigWarning {
+    /// A key under a recognized table that doesn't correspond to any known
+    /// field.
+    UnknownKey {
+        /// Dotted path to the unrecognized key, e.g. `"output.html.theem"`.
+        path: String,
+    },
+    /// A key that is recognized, but whose value failed to deserialize into
+    /// its target field's type.
+    WrongType {
+        /// Dotted path to the malformed key.
+        path: String,
+        /// The Rust type the value should have deserialized into.
+        expected: &'static str,
+    },
+}
+
+impl fmt::Display for ConfigWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigWarning::UnknownKey { path } => write!(f, "{}: unknown key", path),
+            ConfigWarning::WrongType { path, expected } => {
+                write!(f, "{}: expected a value that deserializes into {}", path, expected)
+            }
+        }
+    }
+}
+
+/// Check each key of `raw` (a table read from the opaque `rest` bag) by
+/// substituting it into `T`'s serialized default shape: a `WrongType` is
+/// recorded when the result doesn't deserialize into `T`, and an
+/// `UnknownKey` when it does but the key is gone once `T` is serialized
+/// again (no field picked it up). Optional fields that default to `None`
+/// are recognized too, although the default shape leaves them out.
+fn check_table<T>(section: &str, raw: &Table, warnings: &mut Vec<ConfigWarning>)
+where
+    T: Default + Serialize + DeserializeOwned,
+{
+    let shape = match Value::try_from(T::default()) {
+        Ok(Value::Table(shape)) => shape,
+        _ => Table::new(),
+    };
+
+    for (key, value) in raw {
+        let path = format!("{}.{}", section, key);
+
+        let mut probe = shape.clone();
+        probe.insert(key.clone(), value.clone());
+        let parsed = match Value::Table(probe).try_into::<T>() {
+            Ok(parsed) => parsed,
+            Err(_) => {
+                warnings.push(ConfigWarning::WrongType {
+                    path,
+                    expected: std::any::type_name::<T>(),
+                });
+                continue;
+            }
+        };
+
+        match Value::try_from(parsed) {
+            Ok(Value::Table(round_trip)) if round_trip.contains_key(key) => {}
+            _ => warnings.push(ConfigWarning::UnknownKey { path }),
+        }
+    }
+}
+
 impl Serialize for Config {
     fn serialize<S: Serializer>(&self, s: S) -> std::result::Result<S::Ok, S::Error> {
         // TODO: This should probably be removed and use a derive instead.
@@ -230,13 +638,20 @@ impl Serialize for Config {
             table.insert("rust", rust_config);
         }
 
+        if self.fpm != FpmConfig::default() {
+            let fpm_config = Value::try_from(&self.fpm).expect("should always be serializable");
+            table.insert("fpm", fpm_config);
+        }
+
         table.serialize(s)
     }
 }
 
-fn parse_env(key: &str) -> Option<String> {
-    key.strip_prefix("ftd_")
-        .map(|key| key.to_lowercase().replace("__", ".").replace('_', "-"))
+fn parse_env(prefix: &str, key: &str) -> Option<String> {
+    let key = key.to_lowercase();
+    let prefix = format!("{}_", prefix.to_lowercase());
+    let key = key.strip_prefix(&prefix)?;
+    Some(key.replace("__", ".").replace('_', "-"))
 }
 
 /// Configuration options which are specific to the book and required for
@@ -285,6 +700,19 @@ pub struct BuildConfig {
     pub use_default_preprocessors: bool,
     /// Extra directories to trigger rebuild when watching/serving
     pub extra_watch_dirs: Vec<PathBuf>,
+    /// The book's translations, keyed by language code (e.g. `"en"`). If this
+    /// is non-empty, `src/<code>/` is treated as the root of that
+    /// translation's `SUMMARY.md`, rather than `src/` directly.
+    pub languages: HashMap<String, LanguageConfig>,
+    /// The theme directory, relative to the book's root. Shared by every
+    /// renderer backend, so a theme can live outside the book's `src/` and be
+    /// reused across output formats. Defaults to `theme` if `None`.
+    pub theme: Option<PathBuf>,
+    /// Fail the import instead of generating placeholder pages when
+    /// `SUMMARY.md` contains draft chapters (the `[Title]()` form, with no
+    /// linked file). Useful for enforcing that a book has no unwritten
+    /// chapters before it is published.
+    pub disallow_draft_chapters: bool,
 }
 
 impl Default for BuildConfig {
@@ -294,6 +722,109 @@ impl Default for BuildConfig {
             create_missing: true,
             use_default_preprocessors: true,
             extra_watch_dirs: Vec::new(),
+            languages: HashMap::new(),
+            theme: None,
+            disallow_draft_chapters: false,
+        }
+    }
+}
+
+impl BuildConfig {
+    /// Returns the directory of the shared theme, relative to the provided
+    /// root directory. If no theme directory is configured, this defaults to
+    /// `root/theme`.
+    pub fn theme_dir(&self, root: &Path) -> PathBuf {
+        match self.theme {
+            Some(ref d) => root.join(d),
+            None => root.join("theme"),
+        }
+    }
+}
+
+/// The FPM package identity and dependencies used when generating `FPM.ftd`
+/// for the rendered output.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct FpmConfig {
+    /// The package's fully-qualified name, e.g. `example.github.io/my-book`.
+    /// Written to `-- fpm.package:`. If `None`, a placeholder name is used
+    /// and a warning is logged.
+    pub package: Option<String>,
+    /// Base URL packages are downloaded from, written to `download-base-url:`
+    /// beneath `-- fpm.package:`. Omitted if `None`.
+    pub download_base_url: Option<String>,
+    /// Other FPM packages this book depends on, each emitted as its own
+    /// `-- fpm.dependency:` section.
+    pub dependencies: Vec<FpmDependency>,
+    /// Dependency aliases that should be auto-imported into every document,
+    /// each emitted as its own `-- fpm.auto-import:` section.
+    pub auto_imports: Vec<String>,
+}
+
+impl Default for FpmConfig {
+    fn default() -> FpmConfig {
+        FpmConfig {
+            package: None,
+            download_base_url: None,
+            dependencies: vec![FpmDependency {
+                package: "fifthtry.github.io/doc-site".to_string(),
+                alias: Some("ds".to_string()),
+            }],
+            auto_imports: vec!["ds".to_string()],
+        }
+    }
+}
+
+/// A single `-- fpm.dependency:` entry.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct FpmDependency {
+    /// The dependency's fully-qualified package name.
+    pub package: String,
+    /// The local name this dependency is imported as (the `as ds` part of
+    /// `-- fpm.dependency: package as ds`). Defaults to `package` when `None`.
+    pub alias: Option<String>,
+}
+
+impl Default for FpmDependency {
+    fn default() -> FpmDependency {
+        FpmDependency {
+            package: String::new(),
+            alias: None,
+        }
+    }
+}
+
+/// Configuration for a single translation of a multilingual book.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct LanguageConfig {
+    /// The language's human-readable name, e.g. "English".
+    pub name: String,
+    /// Is this the default language, used as a fallback for pages which
+    /// haven't been translated yet?
+    pub default: bool,
+    /// Overrides `book.title` for this language. See
+    /// [`Config::effective_book_config`].
+    pub title: Option<String>,
+    /// Overrides `book.description` for this language.
+    pub description: Option<String>,
+    /// Overrides `book.authors` for this language.
+    pub authors: Option<Vec<String>>,
+    /// The source subdirectory for this language's `SUMMARY.md`, relative to
+    /// `book.src`. Defaults to the language code itself, e.g. `src/fr`.
+    pub src: Option<PathBuf>,
+}
+
+impl Default for LanguageConfig {
+    fn default() -> LanguageConfig {
+        LanguageConfig {
+            name: String::new(),
+            default: false,
+            title: None,
+            description: None,
+            authors: None,
+            src: None,
         }
     }
 }
@@ -335,6 +866,11 @@ pub struct HtmlConfig {
     pub curly_quotes: bool,
     /// Should mathjax be enabled?
     pub mathjax_support: bool,
+    /// Emit native FTD components (`ds.h1`..`ds.h6`, `ds.markdown`, `ds.code`,
+    /// `ds.image`, `ds.ul`/`ds.ol`) for chapter bodies instead of wrapping
+    /// rendered HTML in the `.ftd` output. Defaults to `false` so existing
+    /// books keep rendering the HTML-in-FTD way until they opt in.
+    pub native_ftd: bool,
     /// Whether to fonts.css and respective font files to the output directory.
     pub copy_fonts: bool,
     /// An optional google analytics code.
@@ -385,6 +921,8 @@ pub struct HtmlConfig {
     /// The mapping from old pages to new pages/URLs to use when generating
     /// redirects.
     pub redirect: HashMap<String, String>,
+    /// Code block settings.
+    pub code: Code,
 }
 
 impl Default for HtmlConfig {
@@ -395,6 +933,7 @@ impl Default for HtmlConfig {
             preferred_dark_theme: None,
             curly_quotes: false,
             mathjax_support: false,
+            native_ftd: false,
             copy_fonts: true,
             google_analytics: None,
             additional_css: Vec::new(),
@@ -412,6 +951,7 @@ impl Default for HtmlConfig {
             cname: None,
             live_reload_endpoint: None,
             redirect: HashMap::new(),
+            code: Code::default(),
         }
     }
 }
@@ -446,6 +986,22 @@ impl Default for Print {
     }
 }
 
+/// Configuration for how code blocks are post-processed.
+#[derive(Default, Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Code {
+    /// Per-language hidden-line prefixes, e.g. `{ python = "~", bash = "# " }`.
+    /// A code block whose class is `language-<lang>` has lines starting with
+    /// `<lang>`'s prefix collapsed into `<span class="boring">`, the same way
+    /// `#`-prefixed lines are hidden in Rust snippets.
+    pub hidelines: HashMap<String, String>,
+    /// Path to a `syntaxes.bin` produced by `gen-syntax-cache`, relative to
+    /// the book root. When set, code blocks are highlighted server-side with
+    /// `ClassedHTMLGenerator` instead of being emitted as plain
+    /// `<code class="language-…">` for client-side highlighting.
+    pub syntax_cache: Option<PathBuf>,
+}
+
 /// Configuration for how to fold chapters of sidebar.
 #[derive(Default, Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(default, rename_all = "kebab-case")]
@@ -518,6 +1074,32 @@ pub struct Search {
     /// Copy JavaScript files for the search functionality to the output directory?
     /// Default: `true`.
     pub copy_js: bool,
+    /// Maximum number of characters kept from a section's body when it's
+    /// stored in the search index, so `searchindex.json` doesn't balloon on
+    /// large chapters. Default: `400`.
+    pub max_section_body_chars: u32,
+    /// Only index section headings, skipping their body text entirely.
+    /// Default: `false`.
+    pub headings_only: bool,
+    /// Drop common stop words (the, a, is, ...) from the index. Default: `true`.
+    pub remove_stop_words: bool,
+    /// ISO 639-1 language code selecting the suffix-stripping stemmer used
+    /// when building the index (`"en"` or `"de"` are supported; any other
+    /// value, or `None`, applies no stemming). Lets an importer emit an
+    /// index configured for, say, German content instead of the hard-coded
+    /// English-oriented defaults.
+    pub lang: Option<String>,
+    /// A curated list of terms to exclude from the index, checked in
+    /// addition to (not instead of) `remove_stop_words`'s built-in English
+    /// list. Useful for domain-specific stop words, or any stop words at
+    /// all when `lang` isn't English.
+    pub stop_words: Option<Vec<String>>,
+    /// Terms shorter than this many characters are dropped from the index.
+    /// Combines with `remove_stop_words`/`stop_words` during index
+    /// construction, and with `boost_title`/`boost_hierarchy`/
+    /// `boost_paragraph` when scoring results. Default: `1` (keep
+    /// everything).
+    pub min_word_length: u8,
 }
 
 impl Default for Search {
@@ -534,6 +1116,12 @@ impl Default for Search {
             expand: true,
             heading_split_level: 3,
             copy_js: true,
+            max_section_body_chars: 400,
+            headings_only: false,
+            remove_stop_words: true,
+            lang: None,
+            stop_words: None,
+            min_word_length: 1,
         }
     }
 }
@@ -2,26 +2,37 @@
 extern crate clap;
 extern crate log;
 
-use clap::{ArgMatches, Command};
-
-use std::env;
-use std::path::PathBuf;
+use clap::Command;
+use fpm_importer::utils;
 
 mod cmd;
 
 const VERSION: &str = concat!("v", crate_version!());
 
 fn main() {
-
-
     let command = create_clap_command();
+    let matches = command.get_matches();
 
     // Check which subcommand the user ran...
-    match command.get_matches().subcommand() {
+    let result = match matches.subcommand() {
         Some(("build", sub_matches)) => cmd::build::execute(sub_matches),
+        Some(("watch", sub_matches)) => cmd::watch::execute(sub_matches),
+        Some(("serve", sub_matches)) => cmd::serve::execute(sub_matches),
+        Some(("init", sub_matches)) => cmd::init::execute(sub_matches),
+        Some(("completions", sub_matches)) => {
+            cmd::completions::execute(create_clap_command(), sub_matches)
+        }
+        Some(("gen-syntax-cache", sub_matches)) => cmd::gen_syntax_cache::execute(sub_matches),
+        Some(("remove-hidden-lines", sub_matches)) => {
+            cmd::remove_hidden_lines::execute(sub_matches)
+        }
         _ => unreachable!(),
     };
 
+    if let Err(e) = result {
+        utils::log_backtrace(&e);
+        std::process::exit(1);
+    }
 }
 
 /// Create a list of valid arguments and sub-commands
@@ -36,18 +47,10 @@ fn create_clap_command() -> Command {
              The source code for mdBook is available at: https://github.com/rust-lang/mdBook",
         )
         .subcommand(cmd::build::make_subcommand())
-
-}
-
-fn get_book_dir(args: &ArgMatches) -> PathBuf {
-    if let Some(p) = args.get_one::<PathBuf>("dir") {
-        // Check if path is relative from current dir, or absolute...
-        if p.is_relative() {
-            env::current_dir().unwrap().join(p)
-        } else {
-            p.to_path_buf()
-        }
-    } else {
-        env::current_dir().expect("Unable to determine the current directory")
-    }
+        .subcommand(cmd::watch::make_subcommand())
+        .subcommand(cmd::serve::make_subcommand())
+        .subcommand(cmd::init::make_subcommand())
+        .subcommand(cmd::completions::make_subcommand())
+        .subcommand(cmd::gen_syntax_cache::make_subcommand())
+        .subcommand(cmd::remove_hidden_lines::make_subcommand())
 }
@@ -1,23 +1,44 @@
 use super::{Preprocessor, PreprocessorContext};
 use crate::book::Book;
 use crate::errors::*;
-use log::{debug, trace, warn};
+use log::{debug, error, trace, warn};
 use shlex::Shlex;
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::io::{self, Read, Write};
 use std::process::{Child, Command, Stdio};
 
 /// An example preprocessor is available in this project's `examples/`
 /// directory.
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug)]
 pub struct CmdPreprocessor {
     name: String,
     cmd: String,
+    /// Whether a missing command should be a warning rather than a build
+    /// failure, set from `optional = true` in `[preprocessor.<name>]`.
+    optional: bool,
+    /// Cache of `supports_renderer` answers, keyed by renderer name, so a
+    /// command-based preprocessor is only queried once per renderer rather
+    /// than once per chapter.
+    supports_renderer_cache: RefCell<HashMap<String, bool>>,
 }
 
 impl CmdPreprocessor {
     /// Create a new `CmdPreprocessor`.
     pub fn new(name: String, cmd: String) -> CmdPreprocessor {
-        CmdPreprocessor { name, cmd }
+        CmdPreprocessor {
+            name,
+            cmd,
+            optional: false,
+            supports_renderer_cache: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Mark this preprocessor as optional, so a missing command is treated
+    /// as "unsupported" (with a warning) instead of failing the build.
+    pub fn with_optional(mut self, optional: bool) -> CmdPreprocessor {
+        self.optional = optional;
+        self
     }
 
     /// A convenience function custom preprocessors can use to parse the input
@@ -114,6 +135,22 @@ impl Preprocessor for CmdPreprocessor {
     }
 
     fn supports_renderer(&self, renderer: &str) -> bool {
+        if let Some(answer) = self.supports_renderer_cache.borrow().get(renderer) {
+            return *answer;
+        }
+
+        let answer = self.query_supports_renderer(renderer);
+        self.supports_renderer_cache
+            .borrow_mut()
+            .insert(renderer.to_string(), answer);
+        answer
+    }
+}
+
+impl CmdPreprocessor {
+    /// Spawn `<cmd> supports <renderer>` and treat exit code 0 as "supported"
+    /// (the convention used by the example nop/de-emphasize preprocessors).
+    fn query_supports_renderer(&self, renderer: &str) -> bool {
         debug!(
             "Checking if the \"{}\" preprocessor supports \"{}\"",
             self.name(),
@@ -143,14 +180,29 @@ impl Preprocessor for CmdPreprocessor {
 
         if let Err(ref e) = outcome {
             if e.kind() == io::ErrorKind::NotFound {
-                warn!(
-                    "The command wasn't found, is the \"{}\" preprocessor installed?",
-                    self.name
-                );
-                warn!("\tCommand: {}", self.cmd);
+                if self.optional {
+                    warn!(
+                        "The command `{}` for preprocessor `{}` was not found, \
+                        but was marked as optional.",
+                        self.cmd, self.name
+                    );
+                } else {
+                    error!(
+                        "The command `{0}` wasn't found, is the \"{1}\" preprocessor installed? \
+                        If you want to ignore this error when the \"{1}\" preprocessor is not installed, \
+                        set `optional = true` in the `[preprocessor.{1}]` section of the book.toml configuration file.",
+                        self.cmd, self.name
+                    );
+                }
             }
         }
 
-        outcome.unwrap_or(false)
+        match outcome {
+            Ok(supported) => supported,
+            // A missing, non-optional command reports itself as supported, so
+            // `run` is attempted and fails the build with its own error.
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => !self.optional,
+            Err(_) => false,
+        }
     }
 }
@@ -0,0 +1,164 @@
+use std::ops::Range;
+use std::path::Path;
+
+use log::warn;
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+use super::{Preprocessor, PreprocessorContext};
+use crate::book::{Book, BookItem, Chapter};
+use crate::errors::*;
+use crate::utils::{
+    take_anchored_lines, take_lines, take_rustdoc_include_anchored_lines,
+    take_rustdoc_include_lines,
+};
+
+/// How many times an `{{#include}}` directive may pull in another file that
+/// itself contains `{{#include}}` directives, before we give up. Guards
+/// against include cycles.
+const MAX_INCLUDE_DEPTH: usize = 10;
+
+/// A native preprocessor (it runs in-process, without spawning a child like
+/// [`crate::preprocess::CmdPreprocessor`] does) which splices the contents of
+/// external files into a chapter wherever an include directive appears.
+///
+/// Supported directive forms, resolved relative to the chapter's
+/// `source_path`:
+///
+/// - `{{#include file.rs}}` — the whole file.
+/// - `{{#include file.rs:10:20}}` — lines 10 through 20, inclusive.
+/// - `{{#include file.rs:anchor}}` — the region between a `ANCHOR: anchor`
+///   and `ANCHOR_END: anchor` comment pair.
+/// - `{{#rustdoc_include file.rs:10:20}}` / `{{#rustdoc_include
+///   file.rs:anchor}}` — like the above, but lines outside the requested
+///   range/anchor are kept, hidden behind a leading `# `, so rustdoc can
+///   still compile the surrounding example.
+///
+/// Directives are resolved transitively (an included file's own directives
+/// are expanded too) up to [`MAX_INCLUDE_DEPTH`]. A missing file, bad range,
+/// or unknown anchor leaves a visible error marker in the chapter content and
+/// logs a warning, rather than aborting the whole import.
+#[derive(Default)]
+pub struct IncludePreprocessor;
+
+impl IncludePreprocessor {
+    /// The `IncludePreprocessor`'s name as it appears in `book.toml`.
+    pub const NAME: &'static str = "include";
+
+    /// Create a new `IncludePreprocessor`.
+    pub fn new() -> Self {
+        IncludePreprocessor
+    }
+}
+
+impl Preprocessor for IncludePreprocessor {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn run(&self, ctx: &PreprocessorContext, mut book: Book) -> Result<Book> {
+        let src_dir = ctx.root.join(&ctx.config.book.src);
+        book.for_each_mut(|item| {
+            if let BookItem::Chapter(ref mut ch) = *item {
+                resolve_includes(ch, &src_dir);
+            }
+        });
+
+        Ok(book)
+    }
+}
+
+fn resolve_includes(ch: &mut Chapter, src_dir: &Path) {
+    let base = match ch.source_path.as_ref().and_then(|p| p.parent()) {
+        Some(base) => src_dir.join(base),
+        None => return,
+    };
+
+    ch.content = expand(&ch.content, &base, 0);
+}
+
+static INCLUDE_DIRECTIVE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\{\{\s*#(include|rustdoc_include)\s+([^}]+)\}\}").unwrap());
+
+fn expand(content: &str, base: &Path, depth: usize) -> String {
+    if depth >= MAX_INCLUDE_DEPTH {
+        warn!(
+            "Include depth under {} exceeded {} levels, stopping to avoid a cycle",
+            base.display(),
+            MAX_INCLUDE_DEPTH
+        );
+        return content.to_string();
+    }
+
+    INCLUDE_DIRECTIVE
+        .replace_all(content, |caps: &Captures<'_>| {
+            let is_rustdoc = &caps[1] == "rustdoc_include";
+            let spec = caps[2].trim();
+
+            match resolve_directive(spec, base, is_rustdoc) {
+                Ok(resolved) => expand(&resolved, base, depth + 1),
+                Err(e) => {
+                    warn!("{:#}", e);
+                    format!("**[include error: {:#}]**", e)
+                }
+            }
+        })
+        .into_owned()
+}
+
+fn resolve_directive(spec: &str, base: &Path, is_rustdoc: bool) -> Result<String> {
+    let mut parts = spec.splitn(2, ':');
+    let file = parts.next().unwrap_or("").trim();
+    let rest = parts.next().map(str::trim);
+
+    ensure!(!file.is_empty(), "Empty path in include directive");
+
+    let path = base.join(file);
+    let contents = std::fs::read_to_string(&path)
+        .with_context(|| format!("Unable to open include file {}", path.display()))?;
+
+    let Some(rest) = rest else {
+        return Ok(contents);
+    };
+
+    if let Some(range) = parse_line_range(rest) {
+        let num_lines = contents.lines().count();
+        ensure!(
+            range.start < num_lines,
+            "Invalid line range {} in {}, file only has {} lines",
+            rest,
+            path.display(),
+            num_lines
+        );
+
+        return Ok(if is_rustdoc {
+            take_rustdoc_include_lines(&contents, range)
+        } else {
+            take_lines(&contents, range)
+        });
+    }
+
+    let anchored = if is_rustdoc {
+        take_rustdoc_include_anchored_lines(&contents, rest)
+    } else {
+        take_anchored_lines(&contents, rest)
+    };
+
+    ensure!(
+        !anchored.is_empty(),
+        "Unable to find anchor \"{}\" in {}",
+        rest,
+        path.display()
+    );
+
+    Ok(anchored)
+}
+
+/// Parse a `start:end` line range (1-based, inclusive). Returns `None` if
+/// `spec` has no `:`, in which case it should be treated as an anchor name.
+fn parse_line_range(spec: &str) -> Option<Range<usize>> {
+    let (start, end) = spec.split_once(':')?;
+    let start: usize = start.parse().ok()?;
+    let end: usize = end.parse().ok()?;
+    Some(start.saturating_sub(1)..end)
+}
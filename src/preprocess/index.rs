@@ -0,0 +1,52 @@
+use std::path::Path;
+
+use super::{Preprocessor, PreprocessorContext};
+use crate::book::{Book, BookItem};
+use crate::errors::*;
+
+/// A preprocessor that renames a chapter's `README.md` source to `index.md`,
+/// so it's used as the index page for its directory, as long as there isn't
+/// already a real `index.md` sitting next to it.
+#[derive(Default)]
+pub struct IndexPreprocessor;
+
+impl IndexPreprocessor {
+    /// The `IndexPreprocessor`'s name as it appears in `book.toml`.
+    pub const NAME: &'static str = "index";
+
+    /// Create a new `IndexPreprocessor`.
+    pub fn new() -> Self {
+        IndexPreprocessor
+    }
+}
+
+impl Preprocessor for IndexPreprocessor {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn run(&self, ctx: &PreprocessorContext, mut book: Book) -> Result<Book> {
+        let source_dir = ctx.root.join(&ctx.config.book.src);
+
+        book.for_each_mut(|item| {
+            if let BookItem::Chapter(ref mut ch) = *item {
+                if let Some(ref path) = ch.path {
+                    if is_readme_file(path) && !source_dir.join(path.with_file_name("index.md")).exists() {
+                        let index_md = path.with_file_name("index.md");
+                        ctx.chapter_titles
+                            .borrow_mut()
+                            .insert(index_md.clone(), ch.name.clone());
+                        ch.path = Some(index_md);
+                    }
+                }
+            }
+        });
+
+        Ok(book)
+    }
+}
+
+fn is_readme_file(path: &Path) -> bool {
+    path.file_stem()
+        .map_or(false, |stem| stem.eq_ignore_ascii_case("readme"))
+}
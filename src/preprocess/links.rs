@@ -0,0 +1,33 @@
+use super::{Preprocessor, PreprocessorContext};
+use crate::book::Book;
+use crate::errors::*;
+
+/// Reserved for link-rewriting support that runs before a chapter reaches the
+/// renderer.
+///
+/// Link rewriting for the FTD output currently happens at render time (see
+/// [`crate::utils::render_markdown`]); this preprocessor exists so `links`
+/// can be toggled on or off independently of `index`, and is where
+/// preprocessing-time link rewriting would be added.
+#[derive(Default)]
+pub struct LinkPreprocessor;
+
+impl LinkPreprocessor {
+    /// The `LinkPreprocessor`'s name as it appears in `book.toml`.
+    pub const NAME: &'static str = "links";
+
+    /// Create a new `LinkPreprocessor`.
+    pub fn new() -> Self {
+        LinkPreprocessor
+    }
+}
+
+impl Preprocessor for LinkPreprocessor {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn run(&self, _ctx: &PreprocessorContext, book: Book) -> Result<Book> {
+        Ok(book)
+    }
+}
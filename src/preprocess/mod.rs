@@ -0,0 +1,69 @@
+//! Preprocessors used to transform a [`Book`] before it is handed to a
+//! renderer.
+
+pub use self::cmd::CmdPreprocessor;
+pub use self::include::IncludePreprocessor;
+pub use self::index::IndexPreprocessor;
+pub use self::links::LinkPreprocessor;
+
+mod cmd;
+mod include;
+mod index;
+mod links;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::errors::*;
+
+use crate::book::Book;
+
+/// Anything implementing this trait can be used to preprocess a `Book` before
+/// it is rendered.
+pub trait Preprocessor {
+    /// The unique identifier for this preprocessor, used to toggle it on or
+    /// off via `book.toml`'s `[preprocessor]` table.
+    fn name(&self) -> &str;
+
+    /// Run this preprocessor on the whole book.
+    fn run(&self, ctx: &PreprocessorContext, book: Book) -> Result<Book>;
+
+    /// Does this preprocessor support rendering with the given renderer?
+    ///
+    /// By default, always returns `true`.
+    fn supports_renderer(&self, _renderer: &str) -> bool {
+        true
+    }
+}
+
+/// Extra information for a `Preprocessor` to give them more context when
+/// processing a book.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PreprocessorContext {
+    /// The book's root directory.
+    pub root: PathBuf,
+    /// The book's configuration.
+    pub config: Config,
+    /// The name of the renderer this preprocessing pass is being run for.
+    pub renderer: String,
+    /// Chapter titles, keyed by the chapter's (possibly rewritten) path, for
+    /// preprocessors that change where a chapter is rendered to.
+    #[serde(skip)]
+    pub(crate) chapter_titles: RefCell<HashMap<PathBuf, String>>,
+}
+
+impl PreprocessorContext {
+    /// Create a new `PreprocessorContext`.
+    pub(crate) fn new(root: PathBuf, config: Config, renderer: String) -> Self {
+        PreprocessorContext {
+            root,
+            config,
+            renderer,
+            chapter_titles: RefCell::new(HashMap::new()),
+        }
+    }
+}
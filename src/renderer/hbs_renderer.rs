@@ -1,5 +1,5 @@
 use crate::book::{Book, BookItem};
-use crate::config::{BookConfig, Config, HtmlConfig, Playground, RustEdition};
+use crate::config::{BookConfig, Config, FpmConfig, HtmlConfig, Playground, RustEdition};
 use crate::errors::*;
 use crate::renderer::{RenderContext, Renderer};
 use crate::theme::{self, Theme};
@@ -14,8 +14,17 @@ use std::path::{Path, PathBuf};
 use handlebars::Handlebars;
 use log::{debug, trace, warn};
 use once_cell::sync::Lazy;
+use rayon::prelude::*;
 use regex::{Captures, Regex};
 use serde_json::json;
+use syntect::html::{ClassStyle, ClassedHTMLGenerator};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+
+/// Placeholder body rendered for a draft chapter (a `[Title]()` link in
+/// `SUMMARY.md` with no source file), so a stub page is still emitted and the
+/// sidebar link never dangles.
+const DRAFT_CHAPTER_NOTICE: &str =
+    "*This chapter is a draft and has not been written yet.*";
 
 #[derive(Default)]
 pub struct HtmlHandlebars;
@@ -25,17 +34,23 @@ impl HtmlHandlebars {
         HtmlHandlebars
     }
 
+    /// Renders one chapter to its own `.ftd` file and returns the fragment
+    /// that belongs in the print version (`None` for non-chapter items), so
+    /// callers can dispatch chapters in parallel and reassemble the print
+    /// version afterwards in document order.
     fn render_item(
         &self,
         item: &BookItem,
         mut ctx: RenderItemContext<'_>,
-        print_content: &mut String,
-    ) -> Result<()> {
+    ) -> Result<Option<String>> {
         // FIXME: This should be made DRY-er and rely less on mutable state
 
         let (ch, path) = match item {
-            BookItem::Chapter(ch) if !ch.is_draft_chapter() => (ch, ch.path.as_ref().unwrap()),
-            _ => return Ok(()),
+            BookItem::Chapter(ch) => match ch.path.as_ref() {
+                Some(path) => (ch, path),
+                None => return Ok(None),
+            },
+            _ => return Ok(None),
         };
 
         if let Some(ref edit_url_template) = ctx.html_config.edit_url_template {
@@ -52,19 +67,35 @@ impl HtmlHandlebars {
                 .insert("git_repository_edit_url".to_owned(), json!(edit_url));
         }
 
-        let content = ch.content.clone();
-        let content = utils::render_markdown(&content, ctx.html_config.curly_quotes);
+        let raw_content = if ch.is_draft_chapter() {
+            DRAFT_CHAPTER_NOTICE.to_string()
+        } else {
+            ch.content.clone()
+        };
 
-        let fixed_content =
-            utils::render_markdown_with_path(&ch.content, ctx.html_config.curly_quotes, Some(path));
+        let native_ftd = ctx.html_config.native_ftd;
+        let (content, fixed_content) = if native_ftd {
+            let native = utils::render_markdown_to_ftd(&raw_content, ctx.html_config.curly_quotes);
+            (native.clone(), native)
+        } else {
+            (
+                utils::render_markdown(&raw_content, ctx.html_config.curly_quotes),
+                utils::render_markdown_with_path(
+                    &raw_content,
+                    ctx.html_config.curly_quotes,
+                    Some(path),
+                ),
+            )
+        };
+        let mut print_fragment = String::new();
         if !ctx.is_index && ctx.html_config.print.page_break {
             // Add page break between chapters
             // See https://developer.mozilla.org/en-US/docs/Web/CSS/break-before and https://developer.mozilla.org/en-US/docs/Web/CSS/page-break-before
             // Add both two CSS properties because of the compatibility issue
-            print_content
+            print_fragment
                 .push_str(r#"<div style="break-before: page; page-break-before: always;"></div>"#);
         }
-        print_content.push_str(&fixed_content);
+        print_fragment.push_str(&fixed_content);
 
         // Update the context with data for this file
         let ctx_path = path
@@ -104,12 +135,34 @@ impl HtmlHandlebars {
                 .insert("section".to_owned(), json!(section.to_string()));
         }
 
+        let mut breadcrumbs = ch.parent_names.clone();
+        breadcrumbs.push(ch.name.clone());
+        ctx.data.insert("breadcrumbs".to_owned(), json!(breadcrumbs));
+
+        if let Some(nav) = ctx.navigation.get(path) {
+            if let Some((title, path)) = &nav.previous {
+                ctx.data
+                    .insert("previous".to_owned(), json!({"title": title, "path": path}));
+            }
+            if let Some((title, path)) = &nav.next {
+                ctx.data
+                    .insert("next".to_owned(), json!({"title": title, "path": path}));
+            }
+        }
+
         // Render the handlebars template with the data
         //debug!("Render template");
         let rendered = ctx.handlebars.render("index", &ctx.data)?;
 
-        let rendered =
-            self.post_process(rendered, &ctx.html_config.playground, ctx.edition, &title);
+        let rendered = self.post_process(
+            rendered,
+            &ctx.html_config.playground,
+            ctx.edition,
+            &title,
+            native_ftd,
+            &ctx.html_config.code.hidelines,
+            ctx.syntax_set,
+        );
         //dbg!(&filepath);
         // Write to file
 
@@ -129,13 +182,16 @@ impl HtmlHandlebars {
                 &ctx.html_config.playground,
                 ctx.edition,
                 &title,
+                native_ftd,
+                &ctx.html_config.code.hidelines,
+                ctx.syntax_set,
             );
 
             //dbg!(&ctx.destination);
             utils::fs::write_file(&ctx.destination, "index.ftd", rendered_index.as_bytes())?;
         }
 
-        Ok(())
+        Ok(Some(print_fragment))
     }
 
     fn render_404(
@@ -143,10 +199,10 @@ impl HtmlHandlebars {
         ctx: &RenderContext,
         html_config: &HtmlConfig,
         src_dir: &Path,
-        _handlebars: &mut Handlebars<'_>,
+        handlebars: &mut Handlebars<'_>,
         data: &mut serde_json::Map<String, serde_json::Value>,
+        syntax_set: Option<&SyntaxSet>,
     ) -> Result<()> {
-        //let destination = &ctx.destination;
         let content_404 = if let Some(ref filename) = html_config.input_404 {
             let path = src_dir.join(filename);
             std::fs::read_to_string(&path)
@@ -164,7 +220,11 @@ impl HtmlHandlebars {
                     .to_string()
             }
         };
-        let html_content_404 = utils::render_markdown(&content_404, html_config.curly_quotes);
+        let rendered_content_404 = if html_config.native_ftd {
+            utils::render_markdown_to_ftd(&content_404, html_config.curly_quotes)
+        } else {
+            utils::render_markdown(&content_404, html_config.curly_quotes)
+        };
 
         let mut data_404 = data.clone();
         let base_url = if let Some(site_url) = &html_config.site_url {
@@ -180,7 +240,10 @@ impl HtmlHandlebars {
         data_404.insert("base_url".to_owned(), json!(base_url));
         // Set a dummy path to ensure other paths (e.g. in the TOC) are generated correctly
         data_404.insert("path".to_owned(), json!("404.md"));
-        data_404.insert("content".to_owned(), json!(html_content_404));
+        // The 404 page is always served from the site root, regardless of
+        // how deeply nested the missing path was.
+        data_404.insert("path_to_root".to_owned(), json!(""));
+        data_404.insert("content".to_owned(), json!(rendered_content_404));
 
         let mut title = String::from("Page not found");
         if let Some(book_title) = &ctx.config.book.title {
@@ -188,13 +251,20 @@ impl HtmlHandlebars {
             title.push_str(book_title);
         }
         data_404.insert("title".to_owned(), json!(title));
-        //let rendered = handlebars.render("index", &data_404)?;
 
-        /*let rendered =
-            self.post_process(rendered, &html_config.playground, ctx.config.rust.edition);
-        let output_file = get_404_output_file(&html_config.input_404);
-        utils::fs::write_file(destination, output_file, rendered.as_bytes())?;
-        debug!("Creating 404.html ✓");*/
+        let rendered = handlebars.render("index", &data_404)?;
+        let rendered = self.post_process(
+            rendered,
+            &html_config.playground,
+            ctx.config.rust.edition,
+            &title,
+            html_config.native_ftd,
+            &html_config.code.hidelines,
+            syntax_set,
+        );
+        let output_file = utils::fs::get_404_output_file(&html_config.input_404);
+        debug!("Creating {} ✓", output_file);
+        utils::fs::write_file(&ctx.destination, output_file, rendered.as_bytes())?;
         Ok(())
     }
 
@@ -205,42 +275,42 @@ impl HtmlHandlebars {
         playground_config: &Playground,
         edition: Option<RustEdition>,
         title: &String,
+        native_ftd: bool,
+        hidelines: &HashMap<String, String>,
+        syntax_set: Option<&SyntaxSet>,
     ) -> String {
-        //dbg!(&rendered);
         let rendered = embed_title(&rendered, title);
-        //let rendered = build_header_links(&rendered);
-        //let rendered = build_paragraph_with_markdown(&rendered);
-        //dbg!("headers",&rendered);
-        let rendered = fix_code_blocks(&rendered);
-        //dbg!("block",&rendered);
-        let rendered = add_playground_pre(&rendered, playground_config, edition);
-        let rendered = remove_whitespaces(&rendered);
-        rendered
+        let rendered = if native_ftd {
+            // Native FTD components are emitted directly, not `<code>`/HTML,
+            // so the HTML-only fixups below would be no-ops at best.
+            rendered
+        } else {
+            let rendered = fix_code_blocks(&rendered);
+            add_playground_pre(&rendered, playground_config, edition, hidelines, syntax_set)
+        };
+        remove_whitespaces(&rendered)
     }
 
-    fn copy_static_files(&self, destination: &Path) -> Result<()> {
+    fn copy_static_files(
+        &self,
+        destination: &Path,
+        theme: &Theme,
+        config: &Config,
+        book: &Book,
+        html_config: &HtmlConfig,
+    ) -> Result<()> {
         use crate::utils::fs::write_file;
 
+        write_file(destination, "theme.css", &theme.css)?;
+
+        for (path, contents) in &theme.files {
+            write_file(destination, path, contents)?;
+        }
+
         write_file(
             destination,
             "FPM.ftd",
-            remove_whitespaces(
-                "-- import: fpm
-
-            -- fpm.package: wasif1024.github.io/fpm-site
-            download-base-url: https://raw.githubusercontent.com/wasif1024/fpm-site/main
-            
-            -- fpm.dependency: fifthtry.github.io/doc-site as ds
-            
-            -- fpm.auto-import: ds
-            
-            -- fpm.sitemap:
-            
-            # Home: /
-            nav-title: Home
-            data: Section Data",
-            )
-            .as_bytes(),
+            remove_whitespaces(&build_manifest(&config.fpm, book, html_config)).as_bytes(),
         )?;
 
         Ok(())
@@ -327,6 +397,78 @@ impl HtmlHandlebars {
     }
 }
 
+/// Build the contents of `FPM.ftd`: the package manifest driven by
+/// `FpmConfig`, followed by a `-- fpm.sitemap:` that mirrors the book's
+/// table of contents.
+fn build_manifest(fpm: &FpmConfig, book: &Book, html_config: &HtmlConfig) -> String {
+    let package = fpm.package.clone().unwrap_or_else(|| {
+        warn!("`fpm.package` is not set in book.toml; using a placeholder package name");
+        "your-org.github.io/your-site".to_string()
+    });
+
+    let mut manifest = format!("-- import: fpm\n\n-- fpm.package: {}\n", package);
+    if let Some(download_base_url) = &fpm.download_base_url {
+        manifest.push_str(&format!("download-base-url: {}\n", download_base_url));
+    }
+
+    for dependency in &fpm.dependencies {
+        manifest.push_str(&format!("\n-- fpm.dependency: {}", dependency.package));
+        if let Some(alias) = &dependency.alias {
+            manifest.push_str(&format!(" as {}", alias));
+        }
+        manifest.push('\n');
+    }
+
+    for auto_import in &fpm.auto_imports {
+        manifest.push_str(&format!("\n-- fpm.auto-import: {}\n", auto_import));
+    }
+
+    manifest.push_str("\n-- fpm.sitemap:\n");
+    if html_config.input_404 != Some("".to_string()) {
+        // Tells FPM which document to serve whenever a requested path
+        // doesn't match any other sitemap entry.
+        let output_file = utils::fs::get_404_output_file(&html_config.input_404);
+        manifest.push_str(&format!(
+            "404: /{}/\n",
+            output_file.trim_end_matches(".ftd")
+        ));
+    }
+    manifest.push_str(&build_sitemap(book));
+
+    manifest
+}
+
+/// Walk `book.iter()` and emit a `-- fpm.sitemap:` body that reproduces the
+/// table of contents: each chapter becomes an indentation-leveled
+/// `# Title: /path/` entry (indent depth taken from its `SectionNumber`),
+/// part titles become section headers, and separators become blank lines.
+fn build_sitemap(book: &Book) -> String {
+    let mut sitemap = String::new();
+
+    for item in book.iter() {
+        match item {
+            BookItem::Chapter(ch) => {
+                let Some(path) = &ch.path else { continue };
+                let depth = ch.number.as_ref().map(|n| n.0.len()).unwrap_or(1);
+                let indent = "  ".repeat(depth.saturating_sub(1));
+                let route = path.with_extension("");
+                sitemap.push_str(&format!(
+                    "\n{indent}# {title}: /{route}/\n{indent}nav-title: {title}\n",
+                    indent = indent,
+                    title = ch.name,
+                    route = route.display(),
+                ));
+            }
+            BookItem::PartTitle(title) => {
+                sitemap.push_str(&format!("\n-- fpm.sitemap-section:\ntitle: {}\n", title));
+            }
+            BookItem::Separator => sitemap.push('\n'),
+        }
+    }
+
+    sitemap
+}
+
 // TODO(mattico): Remove some time after the 0.1.8 release
 fn maybe_wrong_theme_dir(dir: &Path) -> Result<bool> {
     fn entry_is_maybe_book_file(entry: fs::DirEntry) -> Result<bool> {
@@ -358,7 +500,6 @@ impl Renderer for HtmlHandlebars {
         let src_dir = ctx.root.join(&ctx.config.book.src);
         let destination = &ctx.destination;
         let book = &ctx.book;
-        let build_dir = ctx.root.join(&ctx.config.build.build_dir);
         //dbg!(&book);
         if destination.exists() {
             utils::fs::remove_dir_content(destination)
@@ -376,7 +517,7 @@ impl Renderer for HtmlHandlebars {
                 }
                 dir
             }
-            None => ctx.root.join("theme"),
+            None => ctx.config.build.theme_dir(&ctx.root),
         };
 
         if html_config.theme.is_none()
@@ -393,37 +534,77 @@ impl Renderer for HtmlHandlebars {
 
         debug!("Register the index handlebars template");
         handlebars.register_template_string("index", String::from_utf8(theme.index.clone())?)?;
+        handlebars.register_partial("head", String::from_utf8(theme.head.clone())?)?;
 
         //dbg!("html_config",&html_config);
         //dbg!("handle-bars",&handlebars);
         //dbg!("Mdbook",&book);
         let mut data = make_data(&ctx.root, book, &ctx.config, &html_config, &theme)?;
         //dbg!(&data);
-        // Print version
-        let mut print_content = String::new();
         fs::create_dir_all(&destination)
             .with_context(|| "Unexpected error when constructing destination path")?;
 
+        // Compute "is this the book's index page" for every item up front
+        // (only the first non-draft chapter qualifies), so the per-chapter
+        // rendering below can be dispatched in parallel.
         let mut is_index = true;
-        for item in book.iter() {
-            let ctx = RenderItemContext {
-                handlebars: &handlebars,
-                destination: destination.to_path_buf(),
-                data: data.clone(),
-                is_index,
-                book_config: book_config.clone(),
-                html_config: html_config.clone(),
-                edition: ctx.config.rust.edition,
-                chapter_titles: &ctx.chapter_titles,
-            };
-            self.render_item(item, ctx, &mut print_content)?;
-            // Only the first non-draft chapter item should be treated as the "index"
-            is_index &= !matches!(item, BookItem::Chapter(ch) if !ch.is_draft_chapter());
+        let items: Vec<(&BookItem, bool)> = book
+            .iter()
+            .map(|item| {
+                let qualifies = matches!(item, BookItem::Chapter(ch) if !ch.is_draft_chapter());
+                let item_is_index = is_index && qualifies;
+                is_index &= !qualifies;
+                (item, item_is_index)
+            })
+            .collect();
+
+        let navigation = compute_navigation(book);
+
+        // A precomputed `syntaxes.bin` (see the `gen-syntax-cache` command),
+        // if configured, so code blocks are highlighted server-side instead
+        // of being emitted as plain `<code class="language-…">`.
+        let syntax_set = html_config
+            .code
+            .syntax_cache
+            .as_ref()
+            .map(|path| utils::syntax::load_syntax_cache(&ctx.root.join(path)))
+            .transpose()?;
+
+        let fragments: Vec<Option<String>> = items
+            .par_iter()
+            .map(|(item, item_is_index)| {
+                let render_ctx = RenderItemContext {
+                    handlebars: &handlebars,
+                    destination: destination.to_path_buf(),
+                    data: data.clone(),
+                    is_index: *item_is_index,
+                    book_config: book_config.clone(),
+                    html_config: html_config.clone(),
+                    edition: ctx.config.rust.edition,
+                    chapter_titles: &ctx.chapter_titles,
+                    navigation: &navigation,
+                    syntax_set: syntax_set.as_ref(),
+                };
+                self.render_item(item, render_ctx)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // Print version: reassemble the per-chapter fragments in document order.
+        let mut print_content = String::new();
+        for fragment in fragments.into_iter().flatten() {
+            print_content.push_str(&fragment);
         }
 
         // Render 404 page
         if html_config.input_404 != Some("".to_string()) {
-            self.render_404(ctx, &html_config, &src_dir, &mut handlebars, &mut data)?;
+            self.render_404(
+                ctx,
+                &html_config,
+                &src_dir,
+                &mut handlebars,
+                &mut data,
+                syntax_set.as_ref(),
+            )?;
         }
 
         // Print version
@@ -433,7 +614,7 @@ impl Renderer for HtmlHandlebars {
         }
 
         debug!("Copy static files");
-        self.copy_static_files(destination)
+        self.copy_static_files(destination, &theme, &ctx.config, book, &html_config)
             .with_context(|| "Unable to copy across static files")?;
 
         // Render search index
@@ -448,8 +629,10 @@ impl Renderer for HtmlHandlebars {
         self.emit_redirects(&ctx.destination, &handlebars, &html_config.redirect)
             .context("Unable to emit redirects")?;
 
-        // Copy all remaining files, avoid a recursive copy from/to the book build dir
-        utils::fs::copy_files_except_ext(&src_dir, destination, true, Some(&build_dir), &["md"])?;
+        // Copy all remaining files, skipping the markdown sources themselves
+        let copy_filter = utils::fs::CopyFilter::new(["**/*.md"], std::iter::empty::<&str>())
+            .context("Unable to compile static file copy filter")?;
+        utils::fs::copy_files_except_ext(&src_dir, destination, true, &copy_filter)?;
 
         Ok(())
     }
@@ -618,6 +801,8 @@ fn add_playground_pre(
     html: &str,
     playground_config: &Playground,
     edition: Option<RustEdition>,
+    hidelines: &HashMap<String, String>,
+    syntax_set: Option<&SyntaxSet>,
 ) -> String {
     static ADD_PLAYGROUND_PRE: Lazy<Regex> =
         Lazy::new(|| Regex::new(r##"((?s)<code[^>]?class="([^"]+)".*?>(.*?)</code>)"##).unwrap());
@@ -650,72 +835,415 @@ fn add_playground_pre(
                         }
                     };
 
-                    // wrap the contents in an external pre block
+                    let content: Cow<'_, str> = if playground_config.editable
+                        && classes.contains("editable")
+                        || text.contains("fn main")
+                        || text.contains("quick_main!")
+                    {
+                        code.into()
+                    } else {
+                        // we need to inject our own main
+                        let (attrs, code) = partition_source(code);
+
+                        format!("# #![allow(unused)]\n{}#fn main() {{\n{}#}}", attrs, code).into()
+                    };
+
+                    // Wrap the contents in an external pre block. The full
+                    // de-marked source (header + body + hidden lines) is
+                    // attached to the *wrapper*, not the `<code>`, so the
+                    // editable/runnable playground compiles and runs the
+                    // complete program while `<code>` keeps the boring-span
+                    // presentation for reading.
                     format!(
-                        "<pre class=\"playground\"><code class=\"{}{}\">{}</code></pre>",
+                        "<pre class=\"playground\" data-source=\"{}\"><code class=\"{}{}\">{}</code></pre>",
+                        clean_source(&content, "#", true),
                         classes,
                         edition_class,
-                        {
-                            let content: Cow<'_, str> = if playground_config.editable
-                                && classes.contains("editable")
-                                || text.contains("fn main")
-                                || text.contains("quick_main!")
-                            {
-                                code.into()
-                            } else {
-                                // we need to inject our own main
-                                let (attrs, code) = partition_source(code);
-
-                                format!("# #![allow(unused)]\n{}#fn main() {{\n{}#}}", attrs, code)
-                                    .into()
-                            };
-                            hide_lines(&content)
-                        }
+                        render_code_block(&content, "rust", Some("#"), true, syntax_set)
+                    )
+                } else {
+                    format!(
+                        "<code class=\"{}\" data-source=\"{}\">{}</code>",
+                        classes,
+                        clean_source(code, "#", true),
+                        render_code_block(code, "rust", Some("#"), true, syntax_set)
                     )
+                }
+            } else if let Some(lang) = language_of(classes) {
+                let hide_prefix = hidelines.get(lang).map(String::as_str);
+                let has_syntax =
+                    syntax_set.map_or(false, |ss| ss.find_syntax_by_token(lang).is_some());
+                if hide_prefix.is_some() || has_syntax {
+                    let rendered_code = render_code_block(code, lang, hide_prefix, false, syntax_set);
+                    match hide_prefix {
+                        Some(prefix) => format!(
+                            "<code class=\"{}\" data-source=\"{}\">{}</code>",
+                            classes,
+                            clean_source(code, prefix, false),
+                            rendered_code
+                        ),
+                        None => format!("<code class=\"{}\">{}</code>", classes, rendered_code),
+                    }
                 } else {
-                    format!("<code class=\"{}\">{}</code>", classes, hide_lines(code))
+                    // no configured `hidelines` prefix and no matching cached
+                    // syntax for this language, so no-op
+                    text.to_owned()
                 }
             } else {
-                // not language-rust, so no-op
+                // no `language-<lang>` class at all, so no-op
                 text.to_owned()
             }
         })
         .into_owned()
 }
 
-fn hide_lines(content: &str) -> String {
-    static BORING_LINES_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(\s*)#(.?)(.*)$").unwrap());
+/// Replaces the hidden-line markup in a rendered chapter's code blocks with
+/// plain source: every element carrying a `data-source` attribute (attached
+/// by [`add_playground_pre`], either on the `<pre class="playground">`
+/// wrapper of a runnable/editable Rust block or on a bare `<code>`) is
+/// replaced with that clean, de-marked source and no `boring` or
+/// highlighting markup, dropping the attribute once it's no longer needed.
+/// The hidden lines are not removed: they stay in the output as ordinary,
+/// always-visible lines, since `data-source` holds the full program. Used by the `remove-hidden-lines`
+/// command to produce output suitable for print/no-JS targets, where
+/// neither the boring-line toggle nor syntax-highlighting spans are of any
+/// use. Code blocks with no `data-source` attribute (nothing was hidden) are
+/// left untouched.
+pub fn strip_hidden_lines(html: &str) -> String {
+    static STRIP_PLAYGROUND_DATA_SOURCE: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(
+            r##"(?s)<pre class="playground" data-source="([^"]*)"><code class="([^"]*)">.*?</code></pre>"##,
+        )
+        .unwrap()
+    });
+    static STRIP_CODE_DATA_SOURCE: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r##"(?s)<code class="([^"]*)" data-source="([^"]*)">.*?</code>"##).unwrap()
+    });
+
+    let html = STRIP_PLAYGROUND_DATA_SOURCE.replace_all(html, |caps: &Captures<'_>| {
+        let data_source = &caps[1];
+        let classes = &caps[2];
+        format!(
+            "<pre class=\"playground\"><code class=\"{}\">{}</code></pre>",
+            classes, data_source
+        )
+    });
+
+    STRIP_CODE_DATA_SOURCE
+        .replace_all(&html, |caps: &Captures<'_>| {
+            let classes = &caps[1];
+            let data_source = &caps[2];
+            format!("<code class=\"{}\">{}</code>", classes, data_source)
+        })
+        .into_owned()
+}
+
+/// Pulls the `<lang>` out of a code block's `language-<lang>` class, if any.
+fn language_of(classes: &str) -> Option<&str> {
+    classes
+        .split_whitespace()
+        .find_map(|class| class.strip_prefix("language-"))
+}
 
+/// The outcome of classifying a single line of a code block against a
+/// hide-lines `prefix`: either shown as-is (with any escaping prefixes
+/// un-escaped), or hidden behind a `boring` span.
+enum HiddenLine<'a> {
+    Visible(Cow<'a, str>),
+    Boring(Cow<'a, str>),
+}
+
+/// Classifies one line of a code block for hide-lines purposes, without
+/// deciding how the shown text is rendered, so the plain-text and
+/// syntax-highlighted code paths can share the same hide/show decision.
+///
+/// A run of N consecutive prefixes is an escape: it collapses to N-1 literal
+/// prefixes and the line is shown, uncollapsed, exactly as today's single
+/// `##` -> `#` case, generalized to any count and any prefix. A bare
+/// `prefix`-only line (nothing, or just whitespace, after it) is still
+/// classified as hidden with no leftover marker, and a `prefix` directly
+/// followed by content (no separating space) hides the line with that
+/// content preserved.
+fn classify_hidden_line<'a>(
+    line: &'a str,
+    prefix: &str,
+    rust_attribute_exception: bool,
+) -> HiddenLine<'a> {
+    let indent_len = line.len() - line.trim_start().len();
+    let (indent, rest) = line.split_at(indent_len);
+
+    let mut repeats = 0;
+    let mut remainder = rest;
+    while let Some(stripped) = remainder.strip_prefix(prefix) {
+        repeats += 1;
+        remainder = stripped;
+    }
+
+    if repeats >= 2 {
+        return HiddenLine::Visible(Cow::Owned(format!(
+            "{}{}{}",
+            indent,
+            prefix.repeat(repeats - 1),
+            remainder
+        )));
+    }
+
+    let is_attribute =
+        rust_attribute_exception && (remainder.starts_with('!') || remainder.starts_with('['));
+    if repeats == 1 && !is_attribute {
+        // Eat at most one separating space between the prefix and the rest
+        // of the line.
+        let shown = remainder.strip_prefix(' ').unwrap_or(remainder);
+        return if indent.is_empty() {
+            HiddenLine::Boring(Cow::Borrowed(shown))
+        } else {
+            HiddenLine::Boring(Cow::Owned(format!("{}{}", indent, shown)))
+        };
+    }
+
+    HiddenLine::Visible(Cow::Borrowed(line))
+}
+
+/// Collapses lines of `content` starting with `prefix` (after leading
+/// whitespace) into `<span class="boring">`, so they're hidden until the
+/// reader expands the snippet. See [`classify_hidden_line`] for the
+/// per-line hide/show rules.
+fn hide_lines_impl(content: &str, prefix: &str, rust_attribute_exception: bool) -> String {
     let mut result = String::with_capacity(content.len());
     let mut lines = content.lines().peekable();
     while let Some(line) = lines.next() {
         // Don't include newline on the last line.
         let newline = if lines.peek().is_none() { "" } else { "\n" };
-        if let Some(caps) = BORING_LINES_REGEX.captures(line) {
-            if &caps[2] == "#" {
-                result += &caps[1];
-                result += &caps[2];
-                result += &caps[3];
+        match classify_hidden_line(line, prefix, rust_attribute_exception) {
+            HiddenLine::Visible(shown) => {
+                result += &shown;
                 result += newline;
-                continue;
-            } else if &caps[2] != "!" && &caps[2] != "[" {
+            }
+            HiddenLine::Boring(shown) => {
                 result += "<span class=\"boring\">";
-                result += &caps[1];
-                if &caps[2] != " " {
-                    result += &caps[2];
-                }
-                result += &caps[3];
+                result += &shown;
                 result += newline;
                 result += "</span>";
-                continue;
             }
         }
-        result += line;
+    }
+    result
+}
+
+/// Reconstructs the full, compilable source of a hide-lines code block: every
+/// line, hidden or shown, with its prefix/escape resolved per
+/// [`classify_hidden_line`] and no `boring` HTML wrapping. Attached to each
+/// code block as the `data-source` attribute, so a clipboard-copy action can
+/// recover the complete program rather than just the displayed subset, and
+/// reused by the `remove-hidden-lines` export pass.
+fn clean_source(content: &str, prefix: &str, rust_attribute_exception: bool) -> String {
+    let mut result = String::with_capacity(content.len());
+    let mut lines = content.lines().peekable();
+    while let Some(line) = lines.next() {
+        let newline = if lines.peek().is_none() { "" } else { "\n" };
+        let shown = match classify_hidden_line(line, prefix, rust_attribute_exception) {
+            HiddenLine::Visible(shown) => shown,
+            HiddenLine::Boring(shown) => shown,
+        };
+        result += &shown;
         result += newline;
     }
     result
 }
 
+/// HTML-unescapes `text`, undoing the escaping `pulldown_cmark::html::push_html`
+/// already applied to code block contents, so it can be fed to syntect
+/// (which does its own escaping when generating highlighted HTML). `&amp;`
+/// is unescaped last so a literal `&amp;lt;` in the source doesn't turn into
+/// `<`.
+fn unescape_html(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Highlights `code` with `syntax` using syntect's `ClassedHTMLGenerator`,
+/// collapsing any `hide_prefix`-marked lines into `<span class="boring">`
+/// exactly like [`hide_lines_impl`] does for plain code blocks, so hidden
+/// lines survive highlighting instead of being highlighted themselves.
+fn highlight_and_hide(
+    code: &str,
+    syntax_set: &SyntaxSet,
+    syntax: &SyntaxReference,
+    hide_prefix: Option<&str>,
+    rust_attribute_exception: bool,
+) -> String {
+    let code = unescape_html(code);
+    let mut generator =
+        ClassedHTMLGenerator::new_with_class_style(syntax, syntax_set, ClassStyle::Spaced);
+
+    let mut result = String::with_capacity(code.len());
+    let mut lines = code.lines().peekable();
+    while let Some(line) = lines.next() {
+        let newline = if lines.peek().is_none() { "" } else { "\n" };
+        let (shown, boring) = match hide_prefix {
+            Some(prefix) => match classify_hidden_line(line, prefix, rust_attribute_exception) {
+                HiddenLine::Visible(shown) => (shown, false),
+                HiddenLine::Boring(shown) => (shown, true),
+            },
+            None => (Cow::Borrowed(line), false),
+        };
+
+        let mut highlighted = generator
+            .parse_html_for_line_which_includes_newline(&format!("{}\n", shown))
+            .unwrap_or_default();
+        if highlighted.ends_with('\n') {
+            highlighted.pop();
+        }
+
+        if boring {
+            result += "<span class=\"boring\">";
+            result += &highlighted;
+            result += newline;
+            result += "</span>";
+        } else {
+            result += &highlighted;
+            result += newline;
+        }
+    }
+    result += &generator.finalize();
+    result
+}
+
+/// Renders a fenced code block's contents: highlights with the cached
+/// `SyntaxSet` when a syntax matches `lang`, falling back to the plain
+/// hide-lines passthrough when no cache was configured or no syntax
+/// matches.
+fn render_code_block(
+    code: &str,
+    lang: &str,
+    hide_prefix: Option<&str>,
+    rust_attribute_exception: bool,
+    syntax_set: Option<&SyntaxSet>,
+) -> String {
+    if let Some(syntax_set) = syntax_set {
+        if let Some(syntax) = syntax_set.find_syntax_by_token(lang) {
+            return highlight_and_hide(
+                code,
+                syntax_set,
+                syntax,
+                hide_prefix,
+                rust_attribute_exception,
+            );
+        }
+    }
+
+    match hide_prefix {
+        Some(prefix) => hide_lines_impl(code, prefix, rust_attribute_exception),
+        None => code.to_owned(),
+    }
+}
+
+fn hide_lines(content: &str) -> String {
+    hide_lines_impl(content, "#", true)
+}
+
+/// Like [`hide_lines`], but for an arbitrary `[output.html.code.hidelines]`
+/// prefix instead of Rust's hard-coded `#`. Unlike the Rust path, there's no
+/// `#!`/`#[`-style attribute exception, since arbitrary languages have no
+/// such rule.
+fn hide_lines_with_prefix(content: &str, prefix: &str) -> String {
+    hide_lines_impl(content, prefix, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{clean_source, hide_lines, hide_lines_with_prefix, strip_hidden_lines};
+
+    #[test]
+    fn hides_a_bare_marker_line() {
+        assert_eq!(hide_lines("#\nfn main() {}"), "<span class=\"boring\">\n</span>fn main() {}");
+    }
+
+    #[test]
+    fn hides_a_marker_with_no_separating_space() {
+        assert_eq!(
+            hide_lines("#![allow(unused)]\n#fn main() {\n#}"),
+            "#![allow(unused)]\n<span class=\"boring\">fn main() {\n</span><span class=\"boring\">}</span>"
+        );
+    }
+
+    #[test]
+    fn escapes_a_run_of_markers() {
+        assert_eq!(hide_lines("## still visible"), "# still visible");
+        assert_eq!(hide_lines("### still visible"), "## still visible");
+    }
+
+    #[test]
+    fn keeps_rust_attribute_exceptions_visible() {
+        assert_eq!(hide_lines("#![feature(test)]"), "#![feature(test)]");
+        assert_eq!(hide_lines("#[derive(Debug)]"), "#[derive(Debug)]");
+    }
+
+    #[test]
+    fn generalized_prefix_hides_bare_and_inline_lines() {
+        assert_eq!(
+            hide_lines_with_prefix("~\nprint(1)", "~"),
+            "<span class=\"boring\">\n</span>print(1)"
+        );
+        assert_eq!(
+            hide_lines_with_prefix("~hidden()", "~"),
+            "<span class=\"boring\">hidden()</span>"
+        );
+    }
+
+    #[test]
+    fn generalized_prefix_escapes_a_run_of_markers() {
+        assert_eq!(hide_lines_with_prefix("~~ still visible", "~"), "~ still visible");
+        assert_eq!(hide_lines_with_prefix("~~~ still visible", "~"), "~~ still visible");
+    }
+
+    #[test]
+    fn clean_source_recovers_the_full_program() {
+        assert_eq!(
+            clean_source("#![allow(unused)]\n#fn main() {\n#}", "#", true),
+            "#![allow(unused)]\nfn main() {\n}"
+        );
+    }
+
+    #[test]
+    fn clean_source_resolves_marker_escapes() {
+        assert_eq!(clean_source("## still visible", "#", true), "# still visible");
+        assert_eq!(clean_source("### still visible", "#", true), "## still visible");
+    }
+
+    #[test]
+    fn clean_source_works_with_a_generalized_prefix() {
+        assert_eq!(clean_source("~hidden()", "~", false), "hidden()");
+    }
+
+    #[test]
+    fn strip_hidden_lines_replaces_data_source_code_blocks() {
+        let html = r#"<code class="language-rust" data-source="fn main() {}">fn <span class="boring">main</span>() {}</code>"#;
+        assert_eq!(
+            strip_hidden_lines(html),
+            r#"<code class="language-rust">fn main() {}</code>"#
+        );
+    }
+
+    #[test]
+    fn strip_hidden_lines_leaves_plain_code_blocks_untouched() {
+        let html = r#"<code class="language-python">print(1)</code>"#;
+        assert_eq!(strip_hidden_lines(html), html);
+    }
+
+    #[test]
+    fn strip_hidden_lines_replaces_playground_wrapper_data_source() {
+        let html = r#"<pre class="playground" data-source="fn main() {}"><code class="language-rust">fn <span class="boring">main</span>() {}</code></pre>"#;
+        assert_eq!(
+            strip_hidden_lines(html),
+            r#"<pre class="playground"><code class="language-rust">fn main() {}</code></pre>"#
+        );
+    }
+}
+
 fn partition_source(s: &str) -> (String, String) {
     let mut after_header = false;
     let mut before = String::new();
@@ -746,4 +1274,44 @@ struct RenderItemContext<'a> {
     html_config: HtmlConfig,
     edition: Option<RustEdition>,
     chapter_titles: &'a HashMap<PathBuf, String>,
+    navigation: &'a HashMap<PathBuf, ChapterNavigation>,
+    syntax_set: Option<&'a SyntaxSet>,
+}
+
+/// A chapter's `{ title, path }` neighbours in reading order, so the
+/// rendered page can link to them without re-deriving book order from the
+/// flat `chapters` list.
+#[derive(Debug, Clone, Default)]
+struct ChapterNavigation {
+    previous: Option<(String, String)>,
+    next: Option<(String, String)>,
+}
+
+/// Walk `book.iter()` and, for every chapter with a path, record the
+/// immediately preceding and following non-draft chapters in reading order
+/// (part titles and separators are skipped, but a draft chapter still gets
+/// an entry so its page can link to its nearest non-draft neighbours).
+fn compute_navigation(book: &Book) -> HashMap<PathBuf, ChapterNavigation> {
+    let sequence: Vec<(PathBuf, String, bool)> = book
+        .iter()
+        .filter_map(|item| match item {
+            BookItem::Chapter(ch) => ch
+                .path
+                .as_ref()
+                .map(|path| (path.clone(), ch.name.clone(), ch.is_draft_chapter())),
+            BookItem::PartTitle(_) | BookItem::Separator => None,
+        })
+        .collect();
+
+    let link = |(path, title, _): &(PathBuf, String, bool)| -> Option<(String, String)> {
+        path.to_str().map(|p| (title.clone(), p.to_owned()))
+    };
+
+    let mut navigation = HashMap::new();
+    for (i, (path, _, _)) in sequence.iter().enumerate() {
+        let previous = sequence[..i].iter().rev().find(|(_, _, draft)| !draft).and_then(link);
+        let next = sequence[i + 1..].iter().find(|(_, _, draft)| !draft).and_then(link);
+        navigation.insert(path.clone(), ChapterNavigation { previous, next });
+    }
+    navigation
 }
@@ -1,18 +1,23 @@
 //! [RenderContext]: struct.RenderContext.html
 
-pub use self::hbs_renderer::HtmlHandlebars;
+pub use self::hbs_renderer::{strip_hidden_lines, HtmlHandlebars};
 pub use self::markdown_renderer::MarkdownRenderer;
 
 //mod html_handlebars;
 mod hbs_renderer;
 mod markdown_renderer;
+#[cfg(feature = "search")]
+mod search;
 
 use shlex::Shlex;
 use std::collections::HashMap;
 use std::fs;
-use std::io::{self, ErrorKind, Read};
+use std::io::{self, ErrorKind, Read, Write};
 use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::Duration;
+use wait_timeout::ChildExt;
 
 use crate::book::Book;
 use crate::config::Config;
@@ -22,7 +27,9 @@ use toml::Value;
 
 use serde::{Deserialize, Serialize};
 
-pub trait Renderer {
+/// `Send + Sync` so [`crate::book::MDBook::build`] can run independent
+/// renderers concurrently on worker threads.
+pub trait Renderer: Send + Sync {
     fn name(&self) -> &str;
 
     fn render(&self, ctx: &RenderContext) -> Result<()>;
@@ -31,6 +38,12 @@ pub trait Renderer {
 /// The context provided to all renderers.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RenderContext {
+    /// The version of this crate that produced this `RenderContext`, so a
+    /// `CmdRenderer` backend can check it is compatible before trusting the
+    /// rest of the JSON schema. Defaults to an empty string when missing, so
+    /// contexts emitted by older hosts still deserialize.
+    #[serde(default)]
+    pub version: String,
     pub root: PathBuf,
     pub book: Book,
     pub config: Config,
@@ -49,6 +62,7 @@ impl RenderContext {
         Q: Into<PathBuf>,
     {
         RenderContext {
+            version: env!("CARGO_PKG_VERSION").to_string(),
             book,
             config,
             root: root.into(),
@@ -67,6 +81,21 @@ impl RenderContext {
     pub fn from_json<R: Read>(reader: R) -> Result<RenderContext> {
         serde_json::from_reader(reader).with_context(|| "Unable to deserialize the `RenderContext`")
     }
+
+    /// Check whether this context's `version` satisfies the given semver
+    /// requirement, so a backend can refuse to run against an incompatible
+    /// host instead of producing garbage output. A missing or unparsable
+    /// `version` (e.g. from an older host) is treated as incompatible.
+    pub fn is_compatible(&self, req: &str) -> bool {
+        let req = match semver::VersionReq::parse(req) {
+            Ok(req) => req,
+            Err(_) => return false,
+        };
+        match semver::Version::parse(&self.version) {
+            Ok(version) => req.matches(&version),
+            Err(_) => false,
+        }
+    }
 }
 
 /// If the subprocess wishes to indicate that rendering failed, it should exit
@@ -178,7 +207,7 @@ impl Renderer for CmdRenderer {
             .compose_command(&ctx.root, &ctx.destination)?
             .stdin(Stdio::piped())
             .stdout(Stdio::inherit())
-            .stderr(Stdio::inherit())
+            .stderr(Stdio::piped())
             .current_dir(&ctx.destination)
             .spawn()
         {
@@ -186,27 +215,82 @@ impl Renderer for CmdRenderer {
             Err(e) => return self.handle_render_command_error(ctx, e),
         };
 
+        // Write the `RenderContext` from a dedicated thread, so a backend
+        // that exits before reading all of stdin can't deadlock the pipe.
         let mut stdin = child.stdin.take().expect("Child has stdin");
-        if let Err(e) = serde_json::to_writer(&mut stdin, &ctx) {
-            // Looks like the backend hung up before we could finish
-            // sending it the render context. Log the error and keep going
-            warn!("Error writing the RenderContext to the backend, {}", e);
-        }
+        let ctx_json = serde_json::to_vec(&ctx)
+            .with_context(|| "Unable to serialize the `RenderContext`")?;
+        let stdin_writer = thread::spawn(move || {
+            if let Err(e) = stdin.write_all(&ctx_json) {
+                // Looks like the backend hung up before we could finish
+                // sending it the render context. Log the error and keep going
+                warn!("Error writing the RenderContext to the backend, {}", e);
+            }
+            // explicitly close the `stdin` file handle
+        });
+
+        // Capture stderr instead of inheriting it, so it can be folded into
+        // the eventual error message rather than lost to the terminal.
+        let mut stderr = child.stderr.take().expect("Child has stderr");
+        let stderr_reader = thread::spawn(move || {
+            let mut buf = Vec::new();
+            let _ = stderr.read_to_end(&mut buf);
+            buf
+        });
 
-        // explicitly close the `stdin` file handle
-        drop(stdin);
+        let status = match self.timeout(ctx) {
+            Some(timeout) => self.wait_with_timeout(&mut child, timeout)?,
+            None => child
+                .wait()
+                .with_context(|| "Error waiting for the backend to complete")?,
+        };
 
-        let status = child
-            .wait()
-            .with_context(|| "Error waiting for the backend to complete")?;
+        let _ = stdin_writer.join();
+        let stderr_output = stderr_reader.join().unwrap_or_default();
 
         trace!("{} exited with output: {:?}", self.cmd, status);
 
         if !status.success() {
             error!("Renderer exited with non-zero return code.");
-            bail!("The \"{}\" renderer failed", self.name);
+            bail!(
+                "The \"{}\" renderer failed:\n--- stderr\n{}",
+                self.name,
+                String::from_utf8_lossy(&stderr_output)
+            );
         } else {
             Ok(())
         }
     }
 }
+
+impl CmdRenderer {
+    /// The `output.<name>.timeout` config value, if any, as a `Duration`.
+    fn timeout(&self, ctx: &RenderContext) -> Option<Duration> {
+        let timeout_key = format!("output.{}.timeout", self.name);
+        match ctx.config.get(&timeout_key) {
+            Some(Value::Integer(secs)) if *secs >= 0 => Some(Duration::from_secs(*secs as u64)),
+            Some(Value::Float(secs)) if *secs >= 0.0 => Some(Duration::from_secs_f64(*secs)),
+            _ => None,
+        }
+    }
+
+    /// Wait for `child` to exit, killing it and failing the build with a
+    /// clear "backend timed out" error if `timeout` elapses first.
+    fn wait_with_timeout(&self, child: &mut Child, timeout: Duration) -> Result<ExitStatus> {
+        match child
+            .wait_timeout(timeout)
+            .with_context(|| "Error waiting for the backend to complete")?
+        {
+            Some(status) => Ok(status),
+            None => {
+                let _ = child.kill();
+                let _ = child.wait();
+                bail!(
+                    "The \"{}\" renderer backend timed out after {:?}",
+                    self.name,
+                    timeout
+                );
+            }
+        }
+    }
+}
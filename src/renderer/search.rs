@@ -0,0 +1,264 @@
+//! Builds a client-side search index (`searchindex.json`) for the FTD
+//! output.
+//!
+//! Unlike the handlebars-rendered `.ftd` bodies (which are either HTML
+//! blobs or native FTD components, see [`crate::utils::render_markdown`]
+//! and [`crate::utils::render_markdown_to_ftd`]), the index is built
+//! straight from the chapter's markdown source: each chapter is split into
+//! sections at heading boundaries, markup is stripped down to plain text,
+//! and an elasticlunr-style inverted index (`term -> [(doc id, term
+//! frequency)]`) is produced alongside a parallel document store.
+
+use crate::book::{Book, BookItem};
+use crate::config::Search;
+use crate::errors::*;
+use crate::utils;
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use pulldown_cmark::{Event, Tag};
+use serde::Serialize;
+
+static STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "for", "if", "in", "into", "is",
+    "it", "no", "not", "of", "on", "or", "such", "that", "the", "their", "then", "there",
+    "these", "they", "this", "to", "was", "will", "with",
+];
+
+/// A single heading-bounded slice of a chapter, as stored in the index.
+#[derive(Debug, Serialize)]
+struct IndexedDocument {
+    title: String,
+    body: String,
+    breadcrumb: String,
+    path: String,
+    /// The FTD heading component's `id`, so a result can deep-link to it
+    /// (empty for a chapter's leading, heading-less section).
+    anchor: String,
+}
+
+/// `term -> [(document index, term frequency)]`, elasticlunr's basic
+/// inverted-index shape.
+#[derive(Default, Serialize)]
+struct SearchIndex {
+    documents: Vec<IndexedDocument>,
+    index: BTreeMap<String, Vec<(usize, usize)>>,
+}
+
+impl SearchIndex {
+    fn add_document(&mut self, search_config: &Search, doc: IndexedDocument) {
+        let doc_id = self.documents.len();
+
+        let mut term_counts: HashMap<String, usize> = HashMap::new();
+        for term in tokenize(&doc.title).chain(tokenize(&doc.body)) {
+            if term.chars().count() < search_config.min_word_length as usize {
+                continue;
+            }
+            if search_config.remove_stop_words && STOP_WORDS.contains(&term.as_str()) {
+                continue;
+            }
+            if let Some(stop_words) = &search_config.stop_words {
+                if stop_words.iter().any(|word| word == &term) {
+                    continue;
+                }
+            }
+            let term = stem(&term, search_config.lang.as_deref());
+            *term_counts.entry(term).or_insert(0) += 1;
+        }
+
+        for (term, frequency) in term_counts {
+            self.index.entry(term).or_default().push((doc_id, frequency));
+        }
+
+        self.documents.push(doc);
+    }
+}
+
+/// Lowercases `text` and splits it into alphanumeric terms.
+fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .map(|term| term.to_lowercase())
+}
+
+/// Reduces `term` to a root form for indexing, so inflected variants
+/// collide in the index instead of being tracked as distinct terms. The
+/// stemmer is selected by `lang` (an ISO 639-1 code); an unrecognized or
+/// absent `lang` applies no stemming, keeping the index's existing
+/// English-oriented defaults.
+///
+/// These are simplified suffix-stripping stemmers, not a full
+/// Porter/Snowball implementation — enough to fold "chapter"/"chapters" or
+/// German "Kapitel"/"Kapiteln" together without a stemming dependency.
+fn stem(term: &str, lang: Option<&str>) -> String {
+    match lang {
+        Some("en") => stem_english(term),
+        Some("de") => stem_german(term),
+        _ => term.to_string(),
+    }
+}
+
+/// Strips a handful of common English inflectional suffixes, longest first.
+fn stem_english(term: &str) -> String {
+    const SUFFIXES: &[&str] = &["edly", "ing", "ies", "ed", "es", "ly", "s"];
+    strip_first_matching_suffix(term, SUFFIXES)
+}
+
+/// Strips a handful of common German inflectional suffixes, longest first.
+fn stem_german(term: &str) -> String {
+    const SUFFIXES: &[&str] = &["ungen", "heit", "keit", "lich", "isch", "ung", "en", "er", "es", "e", "n"];
+    strip_first_matching_suffix(term, SUFFIXES)
+}
+
+/// Removes the first suffix (checked in the given order) that `term` ends
+/// with, provided at least 3 characters remain — short words are left
+/// untouched so stemming doesn't collapse unrelated short terms (e.g. "is",
+/// "as").
+fn strip_first_matching_suffix(term: &str, suffixes: &[&str]) -> String {
+    const MIN_STEM_LEN: usize = 3;
+
+    for suffix in suffixes {
+        if let Some(stem) = term.strip_suffix(suffix) {
+            if stem.chars().count() >= MIN_STEM_LEN {
+                return stem.to_string();
+            }
+        }
+    }
+    term.to_string()
+}
+
+/// A chapter section: the text between one heading (at or above
+/// `heading_split_level`) and the next.
+struct Section {
+    title: String,
+    anchor: String,
+    body: String,
+}
+
+/// Splits `content` into [`Section`]s at heading boundaries, the same way
+/// `utils::render_markdown_to_ftd` emits `ds.h{level}:` components, so the
+/// emitted anchors line up with the ones actually present in the FTD
+/// output.
+fn split_into_sections(content: &str, search_config: &Search) -> Vec<Section> {
+    let parser = utils::new_cmark_parser(content, false);
+    let mut id_counter = HashMap::new();
+
+    let mut sections = Vec::new();
+    let mut current = Section {
+        title: String::new(),
+        anchor: String::new(),
+        body: String::new(),
+    };
+    let mut has_content = false;
+
+    let mut collecting_heading = false;
+    let mut heading_buf = String::new();
+    let mut heading_level = 0u8;
+
+    for event in parser {
+        match event {
+            Event::Start(Tag::Heading(level, ..)) => {
+                collecting_heading = true;
+                heading_level = level as u8;
+                heading_buf.clear();
+            }
+            Event::End(Tag::Heading(..)) => {
+                collecting_heading = false;
+                if heading_level <= search_config.heading_split_level {
+                    if has_content {
+                        sections.push(current);
+                    }
+                    current = Section {
+                        title: heading_buf.trim().to_string(),
+                        anchor: utils::unique_id_from_content(&heading_buf, &mut id_counter),
+                        body: String::new(),
+                    };
+                    has_content = true;
+                } else if !search_config.headings_only {
+                    current.body.push_str(heading_buf.trim());
+                    current.body.push(' ');
+                    has_content = true;
+                }
+            }
+            Event::Text(text) => {
+                if collecting_heading {
+                    heading_buf.push_str(&text);
+                } else if !search_config.headings_only {
+                    current.body.push_str(&text);
+                    current.body.push(' ');
+                    has_content = true;
+                }
+            }
+            Event::Code(text) if !search_config.headings_only => {
+                current.body.push_str(&text);
+                current.body.push(' ');
+                has_content = true;
+            }
+            Event::SoftBreak | Event::HardBreak if !search_config.headings_only => {
+                current.body.push(' ');
+            }
+            _ => {}
+        }
+    }
+
+    if has_content {
+        sections.push(current);
+    }
+
+    sections
+}
+
+/// Keeps at most `max_chars` characters of `text` (cut on a char boundary),
+/// appending `…` when anything was cut off.
+fn truncate_chars(text: &str, max_chars: usize) -> String {
+    let trimmed = text.trim();
+    match trimmed.char_indices().nth(max_chars) {
+        Some((byte_idx, _)) => format!("{}…", &trimmed[..byte_idx]),
+        None => trimmed.to_string(),
+    }
+}
+
+/// Walks `book`, builds the search index and writes it to
+/// `destination/searchindex.json`.
+pub fn create_files(search_config: &Search, destination: &Path, book: &Book) -> Result<()> {
+    let mut index = SearchIndex::default();
+
+    for item in book.iter() {
+        let BookItem::Chapter(ch) = item else {
+            continue;
+        };
+        let Some(path) = &ch.path else {
+            continue;
+        };
+
+        let breadcrumb = ch.parent_names.join(" » ");
+        let route = path.with_extension("");
+        let doc_path = format!("/{}/", route.display());
+
+        for section in split_into_sections(&ch.content, search_config) {
+            let title = if section.title.is_empty() {
+                ch.name.clone()
+            } else {
+                section.title
+            };
+
+            index.add_document(
+                search_config,
+                IndexedDocument {
+                    title,
+                    body: truncate_chars(&section.body, search_config.max_section_body_chars as usize),
+                    breadcrumb: breadcrumb.clone(),
+                    path: doc_path.clone(),
+                    anchor: section.anchor,
+                },
+            );
+        }
+    }
+
+    let contents = serde_json::to_string(&index)
+        .with_context(|| "Unable to serialize the search index")?;
+    utils::fs::write_file(destination, "searchindex.json", contents.as_bytes())?;
+
+    Ok(())
+}
@@ -1,21 +1,32 @@
 #![allow(missing_docs)]
 
-use std::fs::File;
+use std::collections::HashMap;
+use std::fs::{self, File};
 use std::io::Read;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use crate::errors::*;
 use log::warn;
-pub static INDEX: &[u8] = include_bytes!("index.hbs");
-
 
+pub static INDEX: &[u8] = include_bytes!("index.hbs");
+pub static HEAD: &[u8] = include_bytes!("head.hbs");
+pub static CSS: &[u8] = include_bytes!("theme.css");
 
 /// You should only ever use the static variables directly if you want to
 /// override the user's theme with the defaults.
 #[derive(Debug, Eq, PartialEq)]
 pub struct Theme {
+    /// The handlebars template used to render every page.
     pub index: Vec<u8>,
-   
+    /// A partial spliced into the `<head>` of every page, for custom
+    /// metadata, fonts, or inline scripts.
+    pub head: Vec<u8>,
+    /// The stylesheet applied to the rendered FTD output.
+    pub css: Vec<u8>,
+    /// Any other file found in `theme_dir` that isn't one of the assets
+    /// above, keyed by its path relative to `theme_dir`. The renderer copies
+    /// these through to the output directory verbatim.
+    pub files: HashMap<PathBuf, Vec<u8>>,
 }
 
 impl Theme {
@@ -30,31 +41,36 @@ impl Theme {
             return theme;
         }
 
-        // Check for individual files, if they exist copy them across
-        {
-            let files = vec![
-                (theme_dir.join("index.hbs"), &mut theme.index),
-            ];
-
-            let load_with_warn = |filename: &Path, dest| {
-                if !filename.exists() {
-                    // Don't warn if the file doesn't exist.
-                    return false;
-                }
-                if let Err(e) = load_file_contents(filename, dest) {
-                    warn!("Couldn't load custom file, {}: {}", filename.display(), e);
-                    false
-                } else {
-                    true
-                }
-            };
-
-            for (filename, dest) in files {
-                load_with_warn(&filename, dest);
+        let load_with_warn = |filename: &Path, dest: &mut Vec<u8>| {
+            if !filename.exists() {
+                // Don't warn if the file doesn't exist.
+                return false;
+            }
+            if let Err(e) = load_file_contents(filename, dest) {
+                warn!("Couldn't load custom file, {}: {}", filename.display(), e);
+                false
+            } else {
+                true
             }
+        };
 
+        // Check for individual files, if they exist copy them across
+        let known_files = [
+            (theme_dir.join("index.hbs"), &mut theme.index),
+            (theme_dir.join("head.hbs"), &mut theme.head),
+            (theme_dir.join("theme.css"), &mut theme.css),
+        ];
+        let known_paths: Vec<PathBuf> = known_files.iter().map(|(p, _)| p.clone()).collect();
+
+        for (filename, dest) in known_files {
+            load_with_warn(&filename, dest);
         }
 
+        // Anything else sitting in the theme directory is an extra static
+        // asset (fonts, images, ...) that the renderer should copy through
+        // verbatim rather than treat as a known, overridable template.
+        collect_extra_files(theme_dir, theme_dir, &known_paths, &mut theme.files);
+
         theme
     }
 }
@@ -63,6 +79,50 @@ impl Default for Theme {
     fn default() -> Theme {
         Theme {
             index: INDEX.to_owned(),
+            head: HEAD.to_owned(),
+            css: CSS.to_owned(),
+            files: HashMap::new(),
+        }
+    }
+}
+
+/// Recursively walks `dir` (a subdirectory of `theme_dir`, or `theme_dir`
+/// itself), collecting every file that isn't one of `known_paths` into
+/// `dest`, keyed by its path relative to `theme_dir`.
+fn collect_extra_files(
+    theme_dir: &Path,
+    dir: &Path,
+    known_paths: &[PathBuf],
+    dest: &mut HashMap<PathBuf, Vec<u8>>,
+) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("Couldn't read theme directory {}: {}", dir.display(), e);
+            return;
+        }
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        if path.is_dir() {
+            collect_extra_files(theme_dir, &path, known_paths, dest);
+            continue;
+        }
+
+        if known_paths.contains(&path) {
+            continue;
+        }
+
+        let mut buffer = Vec::new();
+        if let Err(e) = load_file_contents(&path, &mut buffer) {
+            warn!("Couldn't load theme file, {}: {}", path.display(), e);
+            continue;
+        }
+
+        if let Ok(relative) = path.strip_prefix(theme_dir) {
+            dest.insert(relative.to_path_buf(), buffer);
         }
     }
 }
@@ -1,4 +1,5 @@
 use crate::errors::*;
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use log::{debug, trace};
 use std::convert::Into;
 use std::fs::{self, File};
@@ -80,21 +81,85 @@ pub fn remove_dir_content(dir: &Path) -> Result<()> {
     Ok(())
 }
 
-/// Copies all files of a directory to another one except the files
-/// with the extensions given in the `ext_blacklist` array
+/// A set of gitignore-style glob patterns deciding which files
+/// `copy_files_except_ext` should copy.
+///
+/// Patterns are matched against the file's path relative to the copy's
+/// `from` directory. `include` takes priority over `exclude`, so a whole
+/// tree can be skipped (e.g. `**/*.md`) while still pulling specific files
+/// back in (e.g. `**/keep.md`).
+pub struct CopyFilter {
+    exclude: GlobSet,
+    include: GlobSet,
+}
+
+impl CopyFilter {
+    /// Compile a filter from gitignore-style glob patterns.
+    pub fn new<I, J>(exclude: I, include: J) -> Result<CopyFilter>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+        J: IntoIterator,
+        J::Item: AsRef<str>,
+    {
+        Ok(CopyFilter {
+            exclude: build_glob_set(exclude)?,
+            include: build_glob_set(include)?,
+        })
+    }
+
+    /// Should the file at `relative_path` (relative to the copy's `from`
+    /// directory) be copied?
+    fn allows(&self, relative_path: &Path) -> bool {
+        self.include.is_match(relative_path) || !self.exclude.is_match(relative_path)
+    }
+}
+
+impl Default for CopyFilter {
+    fn default() -> CopyFilter {
+        CopyFilter {
+            exclude: GlobSet::empty(),
+            include: GlobSet::empty(),
+        }
+    }
+}
+
+fn build_glob_set<I>(patterns: I) -> Result<GlobSet>
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let pattern = pattern.as_ref();
+        builder.add(Glob::new(pattern).with_context(|| format!("Invalid glob pattern {:?}", pattern))?);
+    }
+    builder.build().map_err(Into::into)
+}
+
+/// Copies all files of a directory to another one, skipping any file whose
+/// path relative to `from` is rejected by `filter`.
 pub fn copy_files_except_ext(
     from: &Path,
     to: &Path,
     recursive: bool,
-    avoid_dir: Option<&PathBuf>,
-    ext_blacklist: &[&str],
+    filter: &CopyFilter,
+) -> Result<()> {
+    copy_files_with_filter(from, from, to, recursive, filter)
+}
+
+fn copy_files_with_filter(
+    root: &Path,
+    from: &Path,
+    to: &Path,
+    recursive: bool,
+    filter: &CopyFilter,
 ) -> Result<()> {
     debug!(
-        "Copying all files from {} to {} (blacklist: {:?}), avoiding {:?}",
+        "Copying all files from {} to {}, filtered relative to {}",
         from.display(),
         to.display(),
-        ext_blacklist,
-        avoid_dir
+        root.display()
     );
 
     // Check that from and to are different
@@ -108,6 +173,11 @@ pub fn copy_files_except_ext(
             .path()
             .metadata()
             .with_context(|| format!("Failed to read {:?}", entry.path()))?;
+        let relative_path = entry
+            .path()
+            .strip_prefix(root)
+            .expect("entries are always descendants of root")
+            .to_path_buf();
 
         // If the entry is a dir and the recursive option is enabled, call itself
         if metadata.is_dir() && recursive {
@@ -115,30 +185,21 @@ pub fn copy_files_except_ext(
                 continue;
             }
 
-            if let Some(avoid) = avoid_dir {
-                if entry.path() == *avoid {
-                    continue;
-                }
-            }
-
             // check if output dir already exists
             if !to.join(entry.file_name()).exists() {
                 fs::create_dir(&to.join(entry.file_name()))?;
             }
 
-            copy_files_except_ext(
+            copy_files_with_filter(
+                root,
                 &from.join(entry.file_name()),
                 &to.join(entry.file_name()),
                 true,
-                avoid_dir,
-                ext_blacklist,
+                filter,
             )?;
         } else if metadata.is_file() {
-            // Check if it is in the blacklist
-            if let Some(ext) = entry.path().extension() {
-                if ext_blacklist.contains(&ext.to_str().unwrap()) {
-                    continue;
-                }
+            if !filter.allows(&relative_path) {
+                continue;
             }
             debug!(
                 "creating path for file: {:?}",
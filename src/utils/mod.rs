@@ -2,48 +2,21 @@
 
 pub mod fs;
 mod string;
+pub mod syntax;
 pub(crate) mod toml_ext;
 use crate::errors::Error;
 use log::error;
 use once_cell::sync::Lazy;
-use pulldown_cmark::{CodeBlockKind, CowStr, Event, LinkType, Options, Parser, Tag};
+use pulldown_cmark::{html, CodeBlockKind, CowStr, Event, Options, Parser, Tag};
 use regex::Regex;
 
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fmt::Write;
 use std::path::Path;
-pub(crate) enum MarkDownEvents {
-    Heading,
-    Paragraph,
-    Link,
-}
-impl MarkDownEvents {
-    /*const EVENTS_ITER: [MarkDownEvents; 3] = [
-        MarkDownEvents::Heading,
-        MarkDownEvents::Paragraph,
-        MarkDownEvents::Link,
-    ];*/
-    pub(crate) fn as_str(&self) -> &'static str {
-        match self {
-            MarkDownEvents::Heading => "heading",
-            MarkDownEvents::Paragraph => "paragraph",
-            MarkDownEvents::Link => "link",
-        }
-    }
-
-    /*pub(crate) fn from_str(s: &str) -> Self {
-        match s {
-            "heading" => MarkDownEvents::Heading,
-            "paragraph" => MarkDownEvents::Paragraph,
-            "link" => MarkDownEvents::Link,
-            _ => panic!("Invalid Markdown Events {}", s),
-        }
-    }*/
-}
 
 pub use self::string::{
-    take_anchored_lines, take_lines, take_rustdoc_include_anchored_lines,
+    slugify, take_anchored_lines, take_lines, take_rustdoc_include_anchored_lines,
     take_rustdoc_include_lines,
 };
 
@@ -224,14 +197,7 @@ pub fn new_cmark_parser(text: &str, curly_quotes: bool) -> Parser<'_, '_> {
 }
 
 pub fn render_markdown_with_path(text: &str, curly_quotes: bool, path: Option<&Path>) -> String {
-    let mut rendered_docsite = String::with_capacity(text.len() * 3 / 2);
     let p = new_cmark_parser(text, curly_quotes);
-    let mut parsed_str: String;
-    let mut current_tag: String = String::from("");
-    let mut tag_started: bool = false;
-    /*for obj in p{
-        dbg!(obj);
-    }*/
     let events = p
         .map(clean_codeblock_headers)
         .map(|event| adjust_links(event, path))
@@ -240,165 +206,244 @@ pub fn render_markdown_with_path(text: &str, curly_quotes: bool, path: Option<&P
 
             a.into_iter().chain(b)
         });
-    let mut tag_parsed_string = "".to_string();
-    for event in events {
-        (parsed_str, current_tag, tag_started) = render_to_docsite(event, current_tag, tag_started);
-
-        if !tag_started {
-            if current_tag == MarkDownEvents::Link.as_str() {
-                tag_parsed_string = format!("{}{}\n", parsed_str, tag_parsed_string);
-                rendered_docsite = format!("{}{}", rendered_docsite, tag_parsed_string);
-            } else if current_tag == MarkDownEvents::Heading.as_str() {
-                tag_parsed_string = format!("{}{}\n", tag_parsed_string, parsed_str);
-                rendered_docsite = format!("{}{}", rendered_docsite, tag_parsed_string);
-            }else {
-                tag_parsed_string = format!("{}\n{}", tag_parsed_string, parsed_str);
-                rendered_docsite = format!("{}{}", rendered_docsite, tag_parsed_string);
-            }
-            tag_parsed_string = "".to_string();
-        } else {
-            dbg!("tag closed");
-            dbg!(&current_tag);
-            tag_parsed_string = parsed_str;
-        }
-    }
 
-    rendered_docsite
+    let mut html_content = String::with_capacity(text.len() * 3 / 2);
+    html::push_html(&mut html_content, events);
+    html_content
 }
-pub fn render_to_docsite(
-    event: Event,
-    mut current_tag: String,
-    mut tag_started: bool,
-) -> (String, String, bool) {
-    let mut result_str = String::from("");
-    //let mut tag_type = String::from("heading");
-    dbg!(&event);
-    match &event {
-        Event::Start(tag) => match tag {
-            Tag::Heading(heading_level, _fragment_identifier, _class_list) => {
-                tag_started = true;
-                current_tag = MarkDownEvents::Heading.as_str().to_string();
-                result_str = format!(
-                    r##"-- ds.{heading_level}: "##
-                );
-            }
-            Tag::Paragraph => {
-                tag_started = true;
-                current_tag = MarkDownEvents::Paragraph.as_str().to_string();
-                dbg!("in paragraph");
-                result_str = r##"-- ds.markdown: "##.to_string();
-            }
-            Tag::Link(link_type, url, _title) => {
-                tag_started = true;
-                if *link_type == LinkType::Inline {
-                    current_tag = MarkDownEvents::Link.as_str().to_string();
-                    let parsed_url = url.to_string().replace(".ftd", "");
-                    result_str = format!(r##"(/{parsed_url}/)"##);
-                }
-            }
-            Tag::List(ordered_list_first_item_number) => {
-                tag_started = true;
-                println!(
-                    "List ordered_list_first_item_number: {:?}",
-                    ordered_list_first_item_number
-                )
+
+/// Alternative to [`render_markdown`] that emits native FTD components
+/// (`ds.h1`..`ds.h6`, `ds.markdown`, `ds.code`, `ds.image`, `ds.ul`/`ds.ol`)
+/// instead of an HTML blob, for use when `HtmlConfig::native_ftd` is set.
+///
+/// Inline emphasis, strong, strikethrough, code spans and links are
+/// preserved as FTD-flavoured markdown inside `ds.markdown:` bodies, since
+/// that component itself accepts markdown.
+pub fn render_markdown_to_ftd(text: &str, curly_quotes: bool) -> String {
+    let parser = new_cmark_parser(text, curly_quotes);
+
+    let mut out = String::with_capacity(text.len() * 3 / 2);
+    let mut id_counter = HashMap::new();
+
+    // One frame per currently-open list item, plus a permanent base frame
+    // (index 0) for inline content (headings, top-level paragraphs) that
+    // isn't inside any item. Pushing/popping a frame per `Item` keeps a
+    // nested list's own item from clobbering its parent item's
+    // still-pending text, which a single shared `inline_events` cannot.
+    struct InlineFrame<'a> {
+        collecting: bool,
+        events: Vec<Event<'a>>,
+        /// Markdown for any list nested directly inside this item,
+        /// rendered by `Event::End(Tag::List)` and appended after this
+        /// item's own text once it's flushed.
+        nested: String,
+    }
+
+    let mut frames: Vec<InlineFrame<'_>> = vec![InlineFrame {
+        collecting: false,
+        events: Vec::new(),
+        nested: String::new(),
+    }];
+
+    let mut in_code = false;
+    let mut code_lang = String::new();
+    let mut code_body = String::new();
+
+    let mut list_ordered: Vec<bool> = Vec::new();
+    let mut list_items: Vec<Vec<String>> = Vec::new();
+
+    let mut heading_level = String::new();
+
+    for event in parser {
+        match event {
+            Event::Start(Tag::Heading(level, ..)) => {
+                heading_level = level.to_string();
+                let frame = frames.last_mut().expect("base frame is never popped");
+                frame.collecting = true;
+                frame.events.clear();
             }
-            Tag::Item => {
-                tag_started = true;
-                println!("Item (this is a list item)")
+            Event::End(Tag::Heading(..)) => {
+                let frame = frames.last_mut().expect("base frame is never popped");
+                let content = inline_events_to_markdown(&frame.events);
+                let id = unique_id_from_content(&content, &mut id_counter);
+                let _ = writeln!(out, "-- ds.{}: {}\nid: {}\n", heading_level, content, id);
+                frame.collecting = false;
             }
-            Tag::Emphasis => {
-                tag_started = true;
-                println!("Emphasis (this is a span tag)")
+            Event::Start(Tag::Paragraph) => {
+                let frame = frames.last_mut().expect("base frame is never popped");
+                frame.collecting = true;
+                frame.events.clear();
             }
-            Tag::Strong => {
-                tag_started = true;
-                println!("Strong (this is a span tag)")
+            Event::End(Tag::Paragraph) => {
+                let frame = frames.last_mut().expect("base frame is never popped");
+                frame.collecting = false;
+                if let Some(items) = list_items.last_mut() {
+                    items.push(inline_events_to_markdown(&frame.events));
+                } else if let Some((src, alt)) = sole_image(&frame.events) {
+                    let _ = writeln!(out, "-- ds.image:\nsrc: {}\nalt: {}\n", src, alt);
+                } else {
+                    let content = inline_events_to_markdown(&frame.events);
+                    let _ = writeln!(out, "-- ds.markdown:\n\n{}\n", content);
+                }
             }
-            Tag::Strikethrough => {
-                tag_started = true;
-                println!("Strikethrough (this is a span tag)")
+            Event::Start(Tag::List(start)) => {
+                list_ordered.push(start.is_some());
+                list_items.push(Vec::new());
             }
-            Tag::BlockQuote => {
-                tag_started = true;
-                println!("BlockQuote")
+            Event::End(Tag::List(..)) => {
+                let ordered = list_ordered.pop().unwrap_or(false);
+                let items = list_items.pop().unwrap_or_default();
+                let mut rendered = String::new();
+                let _ = writeln!(rendered, "-- ds.{}:\n", if ordered { "ol" } else { "ul" });
+                for item in items {
+                    let _ = writeln!(rendered, "- {}", item);
+                }
+                rendered.push('\n');
+
+                // A list nested inside another item belongs under that
+                // item, not wherever the parser happens to be in `out`
+                // when it closes.
+                let nested_in_item = frames.len() > 1;
+                if nested_in_item {
+                    frames.last_mut().expect("checked above").nested.push_str(&rendered);
+                } else {
+                    out.push_str(&rendered);
+                }
             }
-            Tag::CodeBlock(code_block_kind) => {
-                tag_started = true;
-                println!("CodeBlock code_block_kind: {:?}", code_block_kind)
+            Event::Start(Tag::Item) => {
+                frames.push(InlineFrame {
+                    collecting: true,
+                    events: Vec::new(),
+                    nested: String::new(),
+                });
             }
-            Tag::Image(_link_type, url, _title) => {
-                tag_started = true;
-                let image_url = url.replace('/', ".");
-                result_str = format!(
-                    r##"-- ds.image: 
-                src: $assets.files{image_url}
-                align: center"##
-                );
+            Event::End(Tag::Item) => {
+                // Tight lists never wrap the item body in a `Paragraph`, so
+                // it's still pending here; loose lists already pushed it via
+                // `Event::End(Tag::Paragraph)` above.
+                let frame = frames.pop().expect("Start(Item) always pushes a frame");
+                if frame.collecting {
+                    if let Some(items) = list_items.last_mut() {
+                        let mut content = inline_events_to_markdown(&frame.events);
+                        if !frame.nested.is_empty() {
+                            content.push_str("\n\n");
+                            content.push_str(&frame.nested);
+                        }
+                        items.push(content);
+                    }
+                } else if !frame.nested.is_empty() {
+                    // A loose item whose own text already flushed via
+                    // `Paragraph`, but that also has a nested list pending.
+                    if let Some(last) = list_items.last_mut().and_then(|items| items.last_mut()) {
+                        last.push_str("\n\n");
+                        last.push_str(&frame.nested);
+                    }
+                }
             }
-            Tag::Table(column_text_alignment_list) => {
-                tag_started = true;
-                println!(
-                    "Table column_text_alignment_list: {:?}",
-                    column_text_alignment_list
-                )
+            Event::Start(Tag::CodeBlock(kind)) => {
+                in_code = true;
+                code_body.clear();
+                code_lang = match kind {
+                    CodeBlockKind::Fenced(info) => {
+                        info.split(',').next().unwrap_or_default().trim().to_string()
+                    }
+                    CodeBlockKind::Indented => String::new(),
+                };
             }
-            Tag::TableHead => {
-                tag_started = true;
-                println!("TableHead (contains TableRow tags")
+            Event::End(Tag::CodeBlock(..)) => {
+                in_code = false;
+                out.push_str("-- ds.code:\n");
+                if !code_lang.is_empty() {
+                    let _ = writeln!(out, "lang: {}", code_lang);
+                }
+                out.push('\n');
+                out.push_str(code_body.trim_end_matches('\n'));
+                out.push_str("\n\n");
             }
-            Tag::TableRow => {
-                tag_started = true;
-                println!("TableRow (contains TableCell tags)")
+            Event::Text(text) if in_code => code_body.push_str(&text),
+            other => {
+                let frame = frames.last_mut().expect("base frame is never popped");
+                if frame.collecting {
+                    frame.events.push(other);
+                }
             }
-            Tag::TableCell => {
-                tag_started = true;
-                println!("TableCell (contains inline tags)")
+        }
+    }
+
+    out
+}
+
+/// Serializes a run of inline events (gathered within a paragraph, heading,
+/// or list item) back into FTD-flavoured markdown text, preserving
+/// emphasis, strong, strikethrough, inline code and links.
+fn inline_events_to_markdown(events: &[Event<'_>]) -> String {
+    let mut out = String::new();
+
+    for event in events {
+        match event {
+            Event::Text(text) => out.push_str(text),
+            Event::Code(code) => {
+                out.push('`');
+                out.push_str(code);
+                out.push('`');
             }
-            Tag::FootnoteDefinition(label) => {
-                tag_started = true;
-                println!("FootnoteDefinition label: {}", label)
+            Event::SoftBreak => out.push(' '),
+            Event::HardBreak => out.push_str("\n\n"),
+            Event::Start(Tag::Emphasis) | Event::End(Tag::Emphasis) => out.push('_'),
+            Event::Start(Tag::Strong) | Event::End(Tag::Strong) => out.push_str("**"),
+            Event::Start(Tag::Strikethrough) | Event::End(Tag::Strikethrough) => {
+                out.push_str("~~")
             }
-        },
-        Event::Text(s) => {
-            tag_started = false;
-            if current_tag == *MarkDownEvents::Heading.as_str().to_string() {
-                result_str = format!(
-                    r##" {s}"##,
-                );
-            } else if current_tag == *MarkDownEvents::Link.as_str().to_string() {
-                result_str = format!(r##"[{s}]"##,);
-            } else if current_tag == *MarkDownEvents::Paragraph.as_str().to_string() {
-                result_str = format!(
-                    r##"{s}"##,
-                );
-            } else {
-                result_str = "".to_string();
+            Event::Start(Tag::Link(..)) => out.push('['),
+            Event::End(Tag::Link(_, dest, _)) => {
+                let _ = write!(out, "]({})", fix_ftd_link_dest(dest));
             }
-
-            //println!("Text: {:?}", s.trim())
+            _ => {}
         }
-        Event::SoftBreak => println!("SoftBreak"),
-        Event::HardBreak => println!("HardBreak"),
-        Event::End(tag) => {
-            tag_started = false;
-            println!("End: {:?}", tag)
+    }
+
+    out
+}
+
+/// If `events` is a single, bare image (the usual shape of a paragraph
+/// containing only `![alt](src)`), return its `(src, alt)` pair.
+fn sole_image(events: &[Event<'_>]) -> Option<(String, String)> {
+    match events {
+        [Event::Start(Tag::Image(_, dest, _)), Event::Text(alt), Event::End(Tag::Image(..))] => {
+            Some((ftd_asset_src(dest), alt.to_string()))
         }
-        Event::Code(s) => {
-            println!("Code: {:?}", s)
+        [Event::Start(Tag::Image(_, dest, _)), Event::End(Tag::Image(..))] => {
+            Some((ftd_asset_src(dest), String::new()))
         }
-        /*Event::Html(s) => println!("Html: {:?}", s),
-        Event::Text(s) => println!("Text: {:?}", s),
+        _ => None,
+    }
+}
 
-        Event::FootnoteReference(s) => println!("FootnoteReference: {:?}", s),
-        Event::TaskListMarker(b) => println!("TaskListMarker: {:?}", b),
+/// Turns an image's relative path into an FTD package-asset reference.
+fn ftd_asset_src(dest: &str) -> String {
+    let relative = dest.trim_start_matches("./").trim_start_matches('/');
+    format!("$assets.files.{}", relative.replace('/', "."))
+}
+
+/// Turns a markdown link destination into an FTD document route: absolute
+/// links and in-page fragments pass through unchanged, everything else is
+/// stripped of its `.md`/`.ftd` extension and rooted (matching the routes
+/// emitted in `build_sitemap`).
+fn fix_ftd_link_dest(dest: &str) -> String {
+    static SCHEME_LINK: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[a-z][a-z0-9+.-]*:").unwrap());
 
-        Event::Rule => println!("Rule"),*/
-        _ => {}
+    if dest.starts_with('#') || SCHEME_LINK.is_match(dest) {
+        return dest.to_string();
     }
-    //String::from("yes")
-    (result_str, current_tag, tag_started)
+
+    let (path, fragment) = match dest.find('#') {
+        Some(idx) => dest.split_at(idx),
+        None => (dest, ""),
+    };
+    let trimmed = path.trim_end_matches(".ftd").trim_end_matches(".md");
+    format!("/{}/{}", trimmed.trim_matches('/'), fragment)
 }
+
 /// Wraps tables in a `.table-wrapper` class to apply overflow-x rules to.
 fn wrap_tables(event: Event<'_>) -> (Option<Event<'_>>, Option<Event<'_>>) {
     match event {
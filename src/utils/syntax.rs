@@ -0,0 +1,75 @@
+//! Building and loading a precomputed syntect [`SyntaxSet`], and dumping
+//! per-theme class-based CSS, for server-side code highlighting.
+//!
+//! The heavy lifting (compiling `.sublime-syntax` files, loading Sublime's
+//! bundled open-source syntaxes) happens once, ahead of time, via the
+//! `gen-syntax-cache` CLI subcommand; the renderer only ever loads the
+//! resulting `syntaxes.bin`, so a book build doesn't pay the parsing cost on
+//! every run.
+
+use crate::errors::*;
+
+use std::fs;
+use std::path::Path;
+
+use syntect::highlighting::ThemeSet;
+use syntect::html::{css_for_theme_with_class_style, ClassStyle};
+use syntect::parsing::{SyntaxSet, SyntaxSetBuilder};
+
+/// The file name [`write_syntax_cache`] gives the cache. The renderer loads
+/// whatever path `output.html.code.syntax-cache` names, so that setting has
+/// to point at this file.
+pub const SYNTAX_CACHE_FILE_NAME: &str = "syntaxes.bin";
+
+/// Build a [`SyntaxSet`] from `.sublime-syntax` files under `source_dir`,
+/// optionally starting from Sublime's bundled open-source syntaxes instead
+/// of an empty set.
+pub fn build_syntax_set(source_dir: &Path, no_default_syntaxes: bool) -> Result<SyntaxSet> {
+    let mut builder = if no_default_syntaxes {
+        SyntaxSetBuilder::new()
+    } else {
+        SyntaxSet::load_defaults_newlines().into_builder()
+    };
+
+    if source_dir.is_dir() {
+        builder
+            .add_from_folder(source_dir, true)
+            .with_context(|| format!("Unable to load syntaxes from {}", source_dir.display()))?;
+    }
+
+    Ok(builder.build())
+}
+
+/// Serialize `syntax_set` to `dest_dir/syntaxes.bin`.
+pub fn write_syntax_cache(syntax_set: &SyntaxSet, dest_dir: &Path) -> Result<()> {
+    fs::create_dir_all(dest_dir)
+        .with_context(|| format!("Unable to create {}", dest_dir.display()))?;
+    let path = dest_dir.join(SYNTAX_CACHE_FILE_NAME);
+    syntect::dumps::dump_to_file(syntax_set, &path)
+        .with_context(|| format!("Unable to write {}", path.display()))
+}
+
+/// Load a previously-written `syntaxes.bin`.
+pub fn load_syntax_cache(path: &Path) -> Result<SyntaxSet> {
+    syntect::dumps::from_dump_file(path)
+        .with_context(|| format!("Unable to load syntax cache {}", path.display()))
+}
+
+/// Dump class-based CSS for every one of syntect's bundled default themes
+/// into `dest_dir/css/syntax/<theme>.css`, so `ClassedHTMLGenerator`'s
+/// `<span class="…">` markup has somewhere to get its colors from.
+pub fn write_theme_css(dest_dir: &Path) -> Result<()> {
+    let css_dir = dest_dir.join("css").join("syntax");
+    fs::create_dir_all(&css_dir)
+        .with_context(|| format!("Unable to create {}", css_dir.display()))?;
+
+    let theme_set = ThemeSet::load_defaults();
+    for (name, theme) in &theme_set.themes {
+        let css = css_for_theme_with_class_style(theme, ClassStyle::Spaced)
+            .with_context(|| format!("Unable to generate CSS for theme {}", name))?;
+        let path = css_dir.join(format!("{}.css", crate::utils::slugify(name)));
+        fs::write(&path, css).with_context(|| format!("Unable to write {}", path.display()))?;
+    }
+
+    Ok(())
+}
@@ -10,21 +10,42 @@ pub(crate) trait TomlExt {
 impl TomlExt for Value {
     fn read(&self, key: &str) -> Option<&Value> {
         if let Some((head, tail)) = split(key) {
-            self.get(head)?.read(tail)
+            self.get_index(head)?.read(tail)
         } else {
-            self.get(key)
+            self.get_index(key)
         }
     }
 
     fn read_mut(&mut self, key: &str) -> Option<&mut Value> {
         if let Some((head, tail)) = split(key) {
-            self.get_mut(head)?.read_mut(tail)
+            self.get_index_mut(head)?.read_mut(tail)
         } else {
-            self.get_mut(key)
+            self.get_index_mut(key)
         }
     }
 
     fn insert(&mut self, key: &str, value: Value) {
+        if let Some(index) = as_array_index(self, key) {
+            let array = self.as_array_mut().expect("unreachable");
+            while array.len() < index {
+                array.push(Value::Table(Table::new()));
+            }
+            if array.len() == index {
+                let filler = match split(key) {
+                    Some((_, tail)) => empty_node_for(tail),
+                    None => Value::Table(Table::new()),
+                };
+                array.push(filler);
+            }
+
+            if let Some((_, tail)) = split(key) {
+                array[index].insert(tail, value);
+            } else {
+                array[index] = value;
+            }
+            return;
+        }
+
         if !self.is_table() {
             *self = Value::Table(Table::new());
         }
@@ -34,7 +55,7 @@ impl TomlExt for Value {
         if let Some((head, tail)) = split(key) {
             table
                 .entry(head)
-                .or_insert_with(|| Value::Table(Table::new()))
+                .or_insert_with(|| empty_node_for(tail))
                 .insert(tail, value);
         } else {
             table.insert(key.to_string(), value);
@@ -43,7 +64,14 @@ impl TomlExt for Value {
 
     fn delete(&mut self, key: &str) -> Option<Value> {
         if let Some((head, tail)) = split(key) {
-            self.get_mut(head)?.delete(tail)
+            self.get_index_mut(head)?.delete(tail)
+        } else if let Value::Array(array) = self {
+            let index: usize = key.parse().ok()?;
+            if index < array.len() {
+                Some(array.remove(index))
+            } else {
+                None
+            }
         } else if let Some(table) = self.as_table_mut() {
             table.remove(key)
         } else {
@@ -52,6 +80,57 @@ impl TomlExt for Value {
     }
 }
 
+/// Get a child of `self` by `key`, indexing into a `Value::Array` when `key`
+/// parses as a non-negative integer and `self` is an array, otherwise
+/// treating `key` as a table key.
+trait IndexOrKey {
+    fn get_index(&self, key: &str) -> Option<&Value>;
+    fn get_index_mut(&mut self, key: &str) -> Option<&mut Value>;
+}
+
+impl IndexOrKey for Value {
+    fn get_index(&self, key: &str) -> Option<&Value> {
+        if let Value::Array(array) = self {
+            if let Ok(index) = key.parse::<usize>() {
+                return array.get(index);
+            }
+        }
+        self.get(key)
+    }
+
+    fn get_index_mut(&mut self, key: &str) -> Option<&mut Value> {
+        if let Value::Array(array) = self {
+            if let Ok(index) = key.parse::<usize>() {
+                return array.get_mut(index);
+            }
+        }
+        self.get_mut(key)
+    }
+}
+
+/// If `node` is an array and the head segment of `key` parses as a
+/// non-negative integer, return that index so `insert` can grow the array
+/// and address the element directly.
+fn as_array_index(node: &Value, key: &str) -> Option<usize> {
+    if !node.is_array() {
+        return None;
+    }
+
+    let head = split(key).map(|(head, _)| head).unwrap_or(key);
+    head.parse().ok()
+}
+
+/// The empty node to create for a not-yet-existing path segment: an array if
+/// the next segment addresses one by numeric index, otherwise a table.
+fn empty_node_for(next_key: &str) -> Value {
+    let head = split(next_key).map(|(head, _)| head).unwrap_or(next_key);
+    if head.parse::<usize>().is_ok() {
+        Value::Array(Vec::new())
+    } else {
+        Value::Table(Table::new())
+    }
+}
+
 fn split(key: &str) -> Option<(&str, &str)> {
     let ix = key.find('.')?;
 
@@ -61,3 +140,50 @@ fn split(key: &str) -> Option<(&str, &str)> {
 
     Some((head, tail))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::TomlExt;
+    use toml::Value;
+
+    #[test]
+    fn insert_grows_array_of_tables_by_default() {
+        let mut root = Value::Table(Default::default());
+        root.insert("matrix.0.name", Value::String("a".to_string()));
+
+        assert_eq!(
+            root.read("matrix.0.name").and_then(Value::as_str),
+            Some("a")
+        );
+    }
+
+    #[test]
+    fn insert_grows_nested_array_when_tail_is_numeric() {
+        let mut root = Value::Table(Default::default());
+        root.insert("matrix.0.0", Value::Integer(1));
+
+        let matrix = root.read("matrix").unwrap();
+        assert!(matrix.get(0).unwrap().is_array());
+        assert_eq!(root.read("matrix.0.0").and_then(Value::as_integer), Some(1));
+    }
+
+    #[test]
+    fn insert_leaves_skipped_array_slots_as_empty_tables() {
+        let mut root = Value::Table(Default::default());
+        root.insert("matrix.2.0", Value::Integer(1));
+
+        let matrix = root.read("matrix").unwrap().as_array().unwrap();
+        assert_eq!(matrix.len(), 3);
+        assert!(matrix[0].is_table());
+        assert!(matrix[1].is_table());
+        assert!(matrix[2].is_array());
+    }
+
+    #[test]
+    fn insert_replaces_array_element_directly_when_key_ends_in_index() {
+        let mut root = Value::Table(Default::default());
+        root.insert("tags.0", Value::String("a".to_string()));
+
+        assert_eq!(root.read("tags.0").and_then(Value::as_str), Some("a"));
+    }
+}